@@ -0,0 +1,249 @@
+pub mod notifier;
+pub mod rule;
+
+use crate::{
+    args::OutputFormat,
+    error::{AppError, AppResult},
+    tuya::{
+        client::TuyaClient,
+        model::{date::year_month_day::YearMonthDay, device::DeviceResponse},
+    },
+    util::{json::to_json, poll, pretty_string::PrettyString},
+};
+use notifier::Notifier;
+use rule::{Condition, Evaluator, Observation, Rule};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use time::{macros::format_description, OffsetDateTime};
+
+/// Alert rules and the notifiers they are sent to, read from a TOML file.
+///
+/// ```toml
+/// [[rule]]
+/// name = "Heater left on"
+/// type = "power"
+/// above = 2000
+/// minutes = 10
+///
+/// [[rule]]
+/// type = "offline"
+/// minutes = 5
+///
+/// [[notifier]]
+/// type = "webhook"
+/// url = "https://example.com/hooks/meters"
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct AlertConfig {
+    #[serde(rename = "rule")]
+    rules: Vec<Rule>,
+    /// Prints to stdout when missing
+    #[serde(rename = "notifier", default = "default_notifiers")]
+    notifiers: Vec<Notifier>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    /// The condition of the rule started to hold
+    Firing,
+    /// The condition of the rule stopped holding
+    Resolved,
+}
+
+/// A rule that started or stopped holding for a device
+#[derive(Serialize, Debug)]
+pub struct Alert {
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+    pub rule: String,
+    pub state: AlertState,
+    pub device: DeviceResponse,
+    pub message: String,
+}
+
+fn default_notifiers() -> Vec<Notifier> {
+    vec![Notifier::Stdout]
+}
+
+impl AlertConfig {
+    /// Alert config in the user's config directory, if there is one
+    pub fn default_path() -> Option<PathBuf> {
+        Some(
+            dirs::config_dir()?
+                .join(env!("CARGO_PKG_NAME"))
+                .join("alerts.toml"),
+        )
+    }
+
+    pub fn load(path: &Path) -> AppResult<Self> {
+        let invalid = |e: &dyn Display| {
+            AppError::Config(format!("Invalid alert config {}: {}", path.display(), e))
+        };
+
+        let s = fs::read_to_string(path)?;
+        let config: Self = toml::from_str(&s).map_err(|e| invalid(&e))?;
+        if config.rules.is_empty() {
+            return Err(invalid(&"at least one [[rule]] is needed"));
+        }
+        for rule in &config.rules {
+            rule.validate().map_err(|e| invalid(&e))?;
+        }
+        for notifier in &config.notifiers {
+            notifier.validate().map_err(|e| invalid(&e))?;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Checks the rules every `interval` and sends the alerts to every notifier, until Ctrl+C is pressed.
+///
+/// Failing requests and notifiers are reported on stderr without stopping, so it can run unattended.
+pub async fn monitor(
+    client: &mut TuyaClient,
+    config: AlertConfig,
+    interval: Duration,
+    output: OutputFormat,
+) -> AppResult<()> {
+    if matches!(output, OutputFormat::Csv | OutputFormat::CsvWide) {
+        return Err(AppError::InvalidArgument(
+            "Alerts can't be exported as CSV, use --output json".into(),
+        ));
+    }
+
+    if output == OutputFormat::Pretty {
+        println!(
+            "Checking the rules ({}) every {}s, press Ctrl+C to stop",
+            config.rules.len(),
+            interval.as_secs()
+        );
+    }
+
+    let mut evaluator = Evaluator::default();
+
+    poll::every(interval, async || {
+        let observations = observe(client, &config.rules).await;
+        let now = OffsetDateTime::now_utc();
+        let now = now.replace_nanosecond(0).unwrap_or(now);
+        let alerts = evaluator.evaluate(&config.rules, &observations, now);
+        for alert in &alerts {
+            for notifier in &config.notifiers {
+                if let Err(e) = notifier.notify(alert, output).await {
+                    eprint!("{}", e);
+                }
+            }
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Reads what the rules need of the devices they apply to
+async fn observe(client: &mut TuyaClient, rules: &[Rule]) -> Vec<Observation> {
+    let devices = match client.get_devices().await {
+        Ok(devices) => devices,
+        Err(e) => {
+            eprint!("Failed to list the devices: {}", e);
+            return Vec::new();
+        }
+    };
+    let today = YearMonthDay::default();
+
+    let mut observations = Vec::new();
+    for device in devices {
+        let rules = rules
+            .iter()
+            .filter(|r| r.applies_to(&device.device_id))
+            .collect::<Vec<_>>();
+        if rules.is_empty() {
+            continue;
+        }
+
+        let props = if rules.iter().any(|r| r.condition.needs_props()) {
+            match client.get_device_properties(&device.device_id).await {
+                Ok(props) => Some(props),
+                Err(e) => {
+                    eprint!("Failed to read {}: {}", device.get_name(), e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let needs_today = rules
+            .iter()
+            .any(|r| matches!(r.condition, Condition::DailyEnergy { .. }));
+        let today = if needs_today {
+            match client
+                .get_daily_device_statistics(&device.device_id, &today, &today)
+                .await
+            {
+                // No statistics yet means nothing was used today
+                Ok(stats) => Some(stats.days.get(&today).copied().unwrap_or_default()),
+                Err(e) => {
+                    eprint!(
+                        "Failed to read today's energy of {}: {}",
+                        device.get_name(),
+                        e
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        observations.push(Observation {
+            device,
+            props,
+            today,
+        });
+    }
+    observations
+}
+
+impl Alert {
+    fn to_json(&self) -> String {
+        to_json(self)
+    }
+}
+
+impl Display for AlertState {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::Firing => write!(f, "firing"),
+            Self::Resolved => write!(f, "resolved"),
+        }
+    }
+}
+
+impl PrettyString for Alert {
+    fn as_pretty_string(&self) -> String {
+        let time = self
+            .time
+            .format(format_description!(
+                "[year]-[month]-[day] [hour]:[minute]:[second]"
+            ))
+            .unwrap_or_default();
+        let state = match self.state {
+            AlertState::Firing => "ALERT",
+            AlertState::Resolved => "RESOLVED",
+        };
+
+        format!(
+            "{} UTC {} {} - {}: {}",
+            time,
+            state,
+            self.rule,
+            self.device.get_name(),
+            self.message
+        )
+    }
+}
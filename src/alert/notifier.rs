@@ -0,0 +1,183 @@
+use super::{Alert, AlertState};
+use crate::{
+    args::OutputFormat,
+    error::{AppError, AppResult},
+    util::{json::print_json, pretty_string::PrettyString},
+};
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use serde::Deserialize;
+use std::{collections::BTreeMap, process::Stdio};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// Where alerts are sent to
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Notifier {
+    /// Prints the alert, as a JSON line with `--output json`
+    Stdout,
+    /// Runs a program with the alert as JSON on stdin and in `TUYA_ALERT_*` environment variables
+    Command {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// POSTs the alert as JSON
+    Webhook {
+        url: String,
+        /// Extra headers, e.g. for authorization
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+    /// Sends the alert as an email
+    Smtp(Smtp),
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Smtp {
+    server: String,
+    /// Defaults to the port of the security
+    port: Option<u16>,
+    #[serde(default)]
+    security: Security,
+    username: Option<String>,
+    password: Option<String>,
+    from: String,
+    to: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Security {
+    /// Upgrades the connection with STARTTLS, port 587
+    #[default]
+    Starttls,
+    /// TLS from the start, port 465
+    Tls,
+    /// Plain text, port 25, only for relays on the local network
+    None,
+}
+
+impl Notifier {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Smtp(smtp) => {
+                if smtp.to.is_empty() {
+                    return Err("an smtp notifier needs at least one address in to".into());
+                }
+                for address in smtp.to.iter().chain([&smtp.from]) {
+                    address
+                        .parse::<Mailbox>()
+                        .map_err(|e| format!("invalid email address {}: {}", address, e))?;
+                }
+                if smtp.username.is_some() != smtp.password.is_some() {
+                    return Err("an smtp notifier needs both username and password".into());
+                }
+            }
+            Self::Stdout | Self::Command { .. } | Self::Webhook { .. } => {}
+        }
+        Ok(())
+    }
+
+    pub async fn notify(&self, alert: &Alert, output: OutputFormat) -> AppResult<()> {
+        match self {
+            Self::Stdout => match output {
+                OutputFormat::Json => print_json(alert),
+                _ => println!("{}", alert.as_pretty_string()),
+            },
+            Self::Command { command, args } => run_command(command, args, alert).await?,
+            Self::Webhook { url, headers } => {
+                let mut request = reqwest::Client::new().post(url).json(alert);
+                for (name, value) in headers {
+                    request = request.header(name, value);
+                }
+                request
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| AppError::Notify(format!("Webhook {} failed: {}", url, e)))?;
+            }
+            Self::Smtp(smtp) => smtp.send(alert).await?,
+        }
+        Ok(())
+    }
+}
+
+async fn run_command(command: &str, args: &[String], alert: &Alert) -> AppResult<()> {
+    let failed = |e: &dyn std::fmt::Display| AppError::Notify(format!("{} failed: {}", command, e));
+
+    let mut child = Command::new(command)
+        .args(args)
+        .env("TUYA_ALERT_RULE", &alert.rule)
+        .env("TUYA_ALERT_STATE", alert.state.to_string())
+        .env("TUYA_ALERT_DEVICE_ID", &alert.device.device_id)
+        .env("TUYA_ALERT_DEVICE_NAME", alert.device.get_name())
+        .env("TUYA_ALERT_MESSAGE", &alert.message)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| failed(&e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The program may not read stdin at all
+        stdin.write_all(alert.to_json().as_bytes()).await.ok();
+    }
+    let status = child.wait().await.map_err(|e| failed(&e))?;
+    if !status.success() {
+        return Err(failed(&status));
+    }
+    Ok(())
+}
+
+impl Smtp {
+    async fn send(&self, alert: &Alert) -> AppResult<()> {
+        let failed = |e: &dyn std::fmt::Display| {
+            AppError::Notify(format!("Email via {} failed: {}", self.server, e))
+        };
+
+        let mut transport = match self.security {
+            Security::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.server)
+                    .map_err(|e| failed(&e))?
+            }
+            Security::Tls => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&self.server).map_err(|e| failed(&e))?
+            }
+            Security::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.server),
+        };
+        if let Some(port) = self.port {
+            transport = transport.port(port);
+        }
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        let state = match alert.state {
+            AlertState::Firing => "Alert",
+            AlertState::Resolved => "Resolved",
+        };
+        // Addresses are checked when the config is loaded
+        let mut email = Message::builder()
+            .from(self.from.parse().map_err(|e| failed(&e))?)
+            .subject(format!(
+                "{}: {} ({})",
+                state,
+                alert.rule,
+                alert.device.get_name()
+            ));
+        for to in &self.to {
+            email = email.to(to.parse().map_err(|e| failed(&e))?);
+        }
+        let email = email
+            .body(alert.as_pretty_string())
+            .map_err(|e| failed(&e))?;
+
+        transport
+            .build()
+            .send(email)
+            .await
+            .map_err(|e| failed(&e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,439 @@
+use super::{Alert, AlertState};
+use crate::tuya::model::{
+    device::{DeviceProperties, DeviceResponse},
+    energy::Energy,
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+use time::OffsetDateTime;
+
+/// A condition that has to hold for a while before it alerts
+#[derive(Deserialize, Debug)]
+pub struct Rule {
+    /// Shown in the alerts, defaults to the condition
+    name: Option<String>,
+    /// IDs of the devices the rule applies to, all devices when missing
+    devices: Option<Vec<String>>,
+    /// Minutes the condition has to hold before the alert fires
+    #[serde(default)]
+    minutes: u32,
+    #[serde(flatten)]
+    pub condition: Condition,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Condition {
+    /// Current power in W
+    Power {
+        above: Option<f64>,
+        below: Option<f64>,
+    },
+    /// Current voltage in V
+    Voltage {
+        above: Option<f64>,
+        below: Option<f64>,
+    },
+    /// Current in A
+    Current {
+        above: Option<f64>,
+        below: Option<f64>,
+    },
+    /// The device isn't connected to the Tuya cloud
+    Offline,
+    /// Consumption of today in kWh
+    DailyEnergy { above: Energy },
+}
+
+/// What is known about a device after a poll, missing when it couldn't be read
+pub struct Observation {
+    pub device: DeviceResponse,
+    pub props: Option<DeviceProperties>,
+    pub today: Option<Energy>,
+}
+
+/// Progress of a rule for a device
+#[derive(Default, Debug)]
+struct RuleState {
+    /// When the condition started to hold
+    since: Option<OffsetDateTime>,
+    firing: bool,
+}
+
+/// Keeps track of how long every rule has been holding for every device
+#[derive(Default, Debug)]
+pub struct Evaluator {
+    /// By rule index and device ID
+    states: HashMap<(usize, String), RuleState>,
+}
+
+impl Rule {
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            let mut name = self.condition.to_string();
+            name[..1].make_ascii_uppercase();
+            name
+        })
+    }
+
+    pub fn applies_to(&self, device_id: &str) -> bool {
+        self.devices
+            .as_ref()
+            .is_none_or(|ids| ids.iter().any(|id| id == device_id))
+    }
+
+    pub fn validate(&self) -> Result<(), &'static str> {
+        match &self.condition {
+            Condition::Power { above, below }
+            | Condition::Voltage { above, below }
+            | Condition::Current { above, below } => {
+                if above.is_none() && below.is_none() {
+                    return Err("power, voltage and current rules need above or below");
+                }
+            }
+            Condition::Offline | Condition::DailyEnergy { .. } => {}
+        }
+        Ok(())
+    }
+}
+
+impl Condition {
+    /// Whether the condition holds, `None` when the observation doesn't tell
+    fn holds(&self, o: &Observation) -> Option<bool> {
+        let outside = |value: Option<f64>, above: &Option<f64>, below: &Option<f64>| {
+            let value = value?;
+            Some(above.is_some_and(|a| value > a) || below.is_some_and(|b| value < b))
+        };
+        let props = o.props.as_ref();
+
+        match self {
+            Self::Power { above, below } => outside(props?.power, above, below),
+            Self::Voltage { above, below } => outside(props?.voltage, above, below),
+            Self::Current { above, below } => outside(props?.current, above, below),
+            Self::Offline => Some(!o.device.is_online),
+            Self::DailyEnergy { above } => Some(o.today? > *above),
+        }
+    }
+
+    /// The value the condition looks at, e.g. "2153.0 W", if it says more than the condition itself
+    fn value(&self, o: &Observation) -> Option<String> {
+        let props = o.props.as_ref();
+        let value = match self {
+            Self::Power { .. } => props.and_then(|p| p.power).map(|v| format!("{:.1} W", v)),
+            Self::Voltage { .. } => props.and_then(|p| p.voltage).map(|v| format!("{:.1} V", v)),
+            Self::Current { .. } => props.and_then(|p| p.current).map(|v| format!("{:.3} A", v)),
+            Self::Offline => return None,
+            Self::DailyEnergy { .. } => o.today.map(|e| e.to_string()),
+        };
+        Some(value.unwrap_or_else(|| "unknown".into()))
+    }
+
+    /// E.g. "power above 2000 W for 10 minutes, now 2153.0 W"
+    fn message(&self, state: AlertState, minutes: u32, o: &Observation) -> String {
+        let mut message = match state {
+            AlertState::Firing => self.to_string(),
+            AlertState::Resolved => format!("no longer {}", self),
+        };
+        if state == AlertState::Firing && minutes > 0 {
+            message += &format!(" for {} minutes", minutes);
+        }
+        if let Some(value) = self.value(o) {
+            message += &format!(", now {}", value);
+        }
+        message
+    }
+
+    pub fn needs_props(&self) -> bool {
+        matches!(
+            self,
+            Self::Power { .. } | Self::Voltage { .. } | Self::Current { .. }
+        )
+    }
+}
+
+impl Display for Condition {
+    /// E.g. "power above 2000 W"
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::Power { above, below } => fmt_range(f, "power", *above, *below, "W"),
+            Self::Voltage { above, below } => fmt_range(f, "voltage", *above, *below, "V"),
+            Self::Current { above, below } => fmt_range(f, "current", *above, *below, "A"),
+            Self::Offline => write!(f, "offline"),
+            Self::DailyEnergy { above } => write!(f, "daily energy above {}", above),
+        }
+    }
+}
+
+fn fmt_range(
+    f: &mut Formatter,
+    name: &str,
+    above: Option<f64>,
+    below: Option<f64>,
+    unit: &str,
+) -> std::fmt::Result {
+    write!(f, "{}", name)?;
+    if let Some(above) = above {
+        write!(f, " above {} {}", above, unit)?;
+    }
+    if above.is_some() && below.is_some() {
+        write!(f, " or")?;
+    }
+    if let Some(below) = below {
+        write!(f, " below {} {}", below, unit)?;
+    }
+    Ok(())
+}
+
+impl Evaluator {
+    /// Alerts that fire or resolve with the observations of the latest poll.
+    ///
+    /// An alert fires once when its condition held for the rule's minutes,
+    /// and resolves once when the condition stops holding.
+    pub fn evaluate(
+        &mut self,
+        rules: &[Rule],
+        observations: &[Observation],
+        now: OffsetDateTime,
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+
+        for (i, rule) in rules.iter().enumerate() {
+            for o in observations {
+                if !rule.applies_to(&o.device.device_id) {
+                    continue;
+                }
+                let Some(holds) = rule.condition.holds(o) else {
+                    continue;
+                };
+
+                let state = self
+                    .states
+                    .entry((i, o.device.device_id.clone()))
+                    .or_default();
+                let alert = |state| Alert {
+                    time: now,
+                    rule: rule.name(),
+                    state,
+                    device: o.device.clone(),
+                    message: rule.condition.message(state, rule.minutes, o),
+                };
+
+                if holds {
+                    let since = *state.since.get_or_insert(now);
+                    if !state.firing && now - since >= time::Duration::minutes(rule.minutes.into())
+                    {
+                        state.firing = true;
+                        alerts.push(alert(AlertState::Firing));
+                    }
+                } else {
+                    state.since = None;
+                    if state.firing {
+                        state.firing = false;
+                        alerts.push(alert(AlertState::Resolved));
+                    }
+                }
+            }
+        }
+
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use time::{macros::datetime, Duration};
+
+    fn rule(toml: &str) -> Rule {
+        toml::from_str(toml).expect("valid rule")
+    }
+
+    fn power_rule() -> Rule {
+        rule(
+            r#"
+            type = "power"
+            above = 2000
+            minutes = 10
+            "#,
+        )
+    }
+
+    fn device() -> DeviceResponse {
+        DeviceResponse {
+            device_id: "dev".into(),
+            product_name: "Smart Plug".into(),
+            custom_name: "Heater".into(),
+            is_online: true,
+        }
+    }
+
+    /// A poll of the device, `power` is `None` when the device couldn't be read
+    fn poll(power: Option<f64>) -> Vec<Observation> {
+        vec![Observation {
+            device: device(),
+            props: power.map(|power| DeviceProperties {
+                power: Some(power),
+                voltage: Some(230.0),
+                current: None,
+                energy: None,
+                properties: Vec::new(),
+            }),
+            today: None,
+        }]
+    }
+
+    fn at(minutes: i64) -> OffsetDateTime {
+        datetime!(2025-01-06 12:00 UTC) + Duration::minutes(minutes)
+    }
+
+    fn states(alerts: &[Alert]) -> Vec<AlertState> {
+        alerts.iter().map(|a| a.state).collect()
+    }
+
+    #[test]
+    fn fires_once_after_the_minutes() {
+        let rules = [power_rule()];
+        let mut evaluator = Evaluator::default();
+
+        assert!(evaluator
+            .evaluate(&rules, &poll(Some(2500.0)), at(0))
+            .is_empty());
+        assert!(evaluator
+            .evaluate(&rules, &poll(Some(2500.0)), at(9))
+            .is_empty());
+
+        let alerts = evaluator.evaluate(&rules, &poll(Some(2500.0)), at(10));
+        assert_eq!(states(&alerts), [AlertState::Firing]);
+        assert_eq!(alerts[0].rule, "Power above 2000 W");
+        assert_eq!(
+            alerts[0].message,
+            "power above 2000 W for 10 minutes, now 2500.0 W"
+        );
+
+        assert!(evaluator
+            .evaluate(&rules, &poll(Some(2600.0)), at(11))
+            .is_empty());
+    }
+
+    #[test]
+    fn dropping_below_starts_over() {
+        let rules = [power_rule()];
+        let mut evaluator = Evaluator::default();
+
+        evaluator.evaluate(&rules, &poll(Some(2500.0)), at(0));
+        evaluator.evaluate(&rules, &poll(Some(1500.0)), at(5));
+        assert!(evaluator
+            .evaluate(&rules, &poll(Some(2500.0)), at(10))
+            .is_empty());
+        assert_eq!(
+            states(&evaluator.evaluate(&rules, &poll(Some(2500.0)), at(20))),
+            [AlertState::Firing]
+        );
+    }
+
+    #[test]
+    fn resolves_once() {
+        let rules = [power_rule()];
+        let mut evaluator = Evaluator::default();
+
+        evaluator.evaluate(&rules, &poll(Some(2500.0)), at(0));
+        evaluator.evaluate(&rules, &poll(Some(2500.0)), at(10));
+
+        let alerts = evaluator.evaluate(&rules, &poll(Some(1500.0)), at(11));
+        assert_eq!(states(&alerts), [AlertState::Resolved]);
+        assert_eq!(
+            alerts[0].message,
+            "no longer power above 2000 W, now 1500.0 W"
+        );
+
+        assert!(evaluator
+            .evaluate(&rules, &poll(Some(1500.0)), at(12))
+            .is_empty());
+    }
+
+    #[test]
+    fn failed_reads_keep_the_state() {
+        let rules = [power_rule()];
+        let mut evaluator = Evaluator::default();
+
+        evaluator.evaluate(&rules, &poll(Some(2500.0)), at(0));
+        assert!(evaluator.evaluate(&rules, &poll(None), at(5)).is_empty());
+        // Still counted from the first poll
+        assert_eq!(
+            states(&evaluator.evaluate(&rules, &poll(Some(2500.0)), at(10))),
+            [AlertState::Firing]
+        );
+        // A failed read doesn't resolve it either
+        assert!(evaluator.evaluate(&rules, &poll(None), at(11)).is_empty());
+        assert_eq!(
+            states(&evaluator.evaluate(&rules, &poll(Some(1500.0)), at(12))),
+            [AlertState::Resolved]
+        );
+    }
+
+    #[test]
+    fn rules_only_apply_to_their_devices() {
+        let rules = [rule(
+            r#"
+            type = "power"
+            above = 2000
+            devices = ["other"]
+            "#,
+        )];
+        let mut evaluator = Evaluator::default();
+
+        assert!(evaluator
+            .evaluate(&rules, &poll(Some(2500.0)), at(0))
+            .is_empty());
+    }
+
+    #[test]
+    fn conditions_hold_outside_their_range() {
+        let range = rule(
+            r#"
+            type = "voltage"
+            above = 250
+            below = 210
+            "#,
+        )
+        .condition;
+        let voltage = |voltage: f64| {
+            let mut o = poll(Some(0.0)).remove(0);
+            o.props.as_mut().unwrap().voltage = Some(voltage);
+            o
+        };
+        assert_eq!(range.holds(&voltage(260.0)), Some(true));
+        assert_eq!(range.holds(&voltage(200.0)), Some(true));
+        assert_eq!(range.holds(&voltage(230.0)), Some(false));
+        assert_eq!(range.holds(&poll(None)[0]), None);
+
+        // The device doesn't report the current
+        let current = rule("type = \"current\"\nabove = 10").condition;
+        assert_eq!(current.holds(&poll(Some(0.0))[0]), None);
+
+        let mut offline = poll(None).remove(0);
+        offline.device.is_online = false;
+        assert_eq!(Condition::Offline.holds(&offline), Some(true));
+
+        let daily = Condition::DailyEnergy {
+            above: Energy::from_kwh(Decimal::from(8)),
+        };
+        assert_eq!(daily.holds(&offline), None);
+        offline.today = Some(Energy::from_kwh(Decimal::from(9)));
+        assert_eq!(daily.holds(&offline), Some(true));
+    }
+
+    #[test]
+    fn ranges_need_a_bound() {
+        assert!(power_rule().validate().is_ok());
+        assert!(rule("type = \"offline\"").validate().is_ok());
+        assert_eq!(
+            rule("type = \"power\"\nminutes = 5").validate(),
+            Err("power, voltage and current rules need above or below")
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{
+    tuya::model::{
+        date::year_month_day::YearMonthDay,
+        device::{DeviceDailyStatistics, DeviceResult},
+        energy::Energy,
+    },
+    util::{csv_export::CsvRows, pretty_string::PrettyString},
+};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+/// Days needed in the window before a day can be judged
+const MIN_WINDOW_DAYS: usize = 3;
+
+/// Spread of a window whose days all used the same, 0.1 kWh.
+/// A day is flagged from a difference of `threshold` times this.
+const MIN_SPREAD_KWH: Decimal = Decimal::from_parts(1, 0, 0, false, 1);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    High,
+    Low,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::High => write!(f, "high"),
+            Self::Low => write!(f, "low"),
+        }
+    }
+}
+
+/// A day whose consumption deviates from the days before it
+#[derive(Serialize, Debug)]
+pub struct Anomaly {
+    pub day: YearMonthDay,
+    pub kwh: Energy,
+    /// Median of the days in the window before
+    pub median_kwh: Energy,
+    /// Robust z-score, based on the median absolute deviation of the window, see [`spread`]
+    pub z_score: Decimal,
+    pub direction: Direction,
+}
+
+impl DeviceResult for Vec<Anomaly> {
+    const KEY: &'static str = "anomalies";
+}
+
+impl CsvRows for Vec<Anomaly> {
+    const COLUMNS: &'static [&'static str] = &["day", "kwh", "median_kwh", "z_score", "direction"];
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.iter()
+            .map(|a| {
+                vec![
+                    a.day.to_string(),
+                    a.kwh.kwh().to_string(),
+                    a.median_kwh.kwh().to_string(),
+                    a.z_score.to_string(),
+                    a.direction.to_string(),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Flags the days from `start` on whose robust z-score exceeds `threshold`,
+/// compared to the `window` days before each of them.
+///
+/// Days with too few values in their window aren't judged.
+pub fn detect(
+    stats: &DeviceDailyStatistics,
+    start: &YearMonthDay,
+    window: i64,
+    threshold: Decimal,
+) -> Vec<Anomaly> {
+    stats
+        .days
+        .range(start..)
+        .filter_map(|(day, kwh)| {
+            let window_start = day.days_before(window)?;
+            let values = stats
+                .days
+                .range(window_start..day.clone())
+                .map(|(_, e)| e.kwh())
+                .collect::<Vec<_>>();
+            if values.len() < MIN_WINDOW_DAYS {
+                return None;
+            }
+
+            let median_kwh = median(values.clone());
+            let z_score = ((kwh.kwh() - median_kwh) / spread(&values, median_kwh)).round_dp(1);
+            (z_score.abs() > threshold).then(|| Anomaly {
+                day: day.clone(),
+                kwh: *kwh,
+                median_kwh: Energy::from_kwh(median_kwh.normalize()),
+                z_score,
+                direction: if z_score.is_sign_positive() {
+                    Direction::High
+                } else {
+                    Direction::Low
+                },
+            })
+        })
+        .collect()
+}
+
+/// Estimate of the standard deviation of the values, from their median absolute deviation.
+///
+/// When most values are the same, e.g. a heater that is usually off, there is none.
+/// Then it comes from the mean absolute deviation, or the minimum when all values are the same.
+fn spread(values: &[Decimal], median_kwh: Decimal) -> Decimal {
+    let deviations = values
+        .iter()
+        .map(|v| (v - median_kwh).abs())
+        .collect::<Vec<_>>();
+
+    let mad = median(deviations.clone());
+    if !mad.is_zero() {
+        // Scales it to the standard deviation of a normal distribution
+        return mad / Decimal::new(6745, 4);
+    }
+    let mean_ad = deviations.iter().sum::<Decimal>() / Decimal::from(deviations.len());
+    if !mean_ad.is_zero() {
+        return mean_ad * Decimal::new(12533, 4);
+    }
+    MIN_SPREAD_KWH
+}
+
+fn median(mut values: Vec<Decimal>) -> Decimal {
+    values.sort();
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / Decimal::TWO
+    } else {
+        values[mid]
+    }
+}
+
+impl PrettyString for Vec<Anomaly> {
+    fn as_pretty_string(&self) -> String {
+        if self.is_empty() {
+            return "No anomalies".into();
+        }
+
+        let padding = self
+            .iter()
+            .map(|a| a.day.as_pretty_string().len())
+            .max()
+            .unwrap_or(0);
+
+        self.iter()
+            .map(|a| {
+                format!(
+                    "* {: <width$} {}, usually {} ({}, z-score {:+})",
+                    a.day.as_pretty_string() + ":",
+                    a.kwh,
+                    a.median_kwh,
+                    a.direction,
+                    a.z_score,
+                    width = padding + 1,
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// Consecutive days from 2024-01-01 on
+    fn stats(kwh: &[i64]) -> DeviceDailyStatistics {
+        let first = YearMonthDay::try_from("20240101").unwrap();
+        let days = kwh
+            .iter()
+            .scan(Some(first), |day, kwh| {
+                let current = day.take()?;
+                *day = current.next_day();
+                Some((current, Energy::from_kwh(Decimal::from(*kwh))))
+            })
+            .collect::<BTreeMap<_, _>>();
+        DeviceDailyStatistics { days }
+    }
+
+    fn day(s: &str) -> YearMonthDay {
+        YearMonthDay::try_from(s).unwrap()
+    }
+
+    #[test]
+    fn spike_after_a_window_of_zeros_is_flagged() {
+        // A heater that is usually off, left on for a day
+        let stats = stats(&[0, 0, 0, 0, 0, 0, 0, 12, 0]);
+        let anomalies = detect(&stats, &day("20240101"), 7, Decimal::new(35, 1));
+
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].day, day("20240108"));
+        assert_eq!(anomalies[0].direction, Direction::High);
+        assert_eq!(anomalies[0].median_kwh, Energy::ZERO);
+    }
+
+    #[test]
+    fn window_with_one_outlier_uses_the_mean_absolute_deviation() {
+        // The median absolute deviation of the window is 0
+        let stats = stats(&[1, 1, 1, 1, 3, 1, 4]);
+        let anomalies = detect(&stats, &day("20240106"), 5, Decimal::new(35, 1));
+
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].day, day("20240107"));
+        assert_eq!(anomalies[0].z_score, Decimal::new(60, 1));
+    }
+
+    #[test]
+    fn usual_days_are_not_flagged() {
+        let stats = stats(&[5, 6, 5, 6, 5, 6, 7, 5, 6, 5]);
+        assert!(detect(&stats, &day("20240101"), 7, Decimal::new(35, 1)).is_empty());
+    }
+}
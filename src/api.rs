@@ -0,0 +1,179 @@
+use crate::{
+    error::AppResult,
+    tuya::{
+        client::TuyaClient,
+        model::{
+            date::{
+                year_month::YearMonth, year_month_day::YearMonthDay,
+                year_month_day_hour::YearMonthDayHour,
+            },
+            error::TuyaError,
+        },
+    },
+};
+use axum::{
+    async_trait,
+    extract::{rejection::QueryRejection, FromRequestParts},
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{net::SocketAddr, sync::Arc};
+use tokio::{net::TcpListener, sync::Mutex};
+
+pub mod device;
+pub mod devices;
+pub mod metrics;
+
+/// A single client is shared between all requests so the access token is reused
+pub type SharedClient = Arc<Mutex<TuyaClient>>;
+
+pub type ApiResult<T> = Result<Json<T>, ApiError>;
+
+pub async fn serve(client: TuyaClient, address: SocketAddr) -> AppResult<()> {
+    let app = router(Arc::new(Mutex::new(client)));
+
+    let listener = TcpListener::bind(address).await?;
+    println!("Listening on http://{}", listener.local_addr()?);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    Ok(())
+}
+
+fn router(client: SharedClient) -> Router {
+    Router::new()
+        .route("/devices", get(devices::list))
+        .route("/devices/stats/daily", get(devices::daily_stats))
+        .route("/devices/stats/monthly", get(devices::monthly_stats))
+        .route("/devices/stats/hourly", get(devices::hourly_stats))
+        .route("/device/:id/info", get(device::info))
+        .route("/device/:id/props", get(device::props))
+        .route("/device/:id/stats/daily", get(device::daily_stats))
+        .route("/device/:id/stats/monthly", get(device::monthly_stats))
+        .route("/device/:id/stats/hourly", get(device::hourly_stats))
+        .route("/metrics", get(metrics::metrics))
+        .with_state(client)
+}
+
+async fn shutdown_signal() {
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Failed to install Ctrl+C handler");
+}
+
+/// Query parameters for daily statistics, defaults to the current month
+#[derive(Deserialize, Debug)]
+pub struct DailyRange {
+    #[serde(default = "YearMonthDay::first_day_current_month")]
+    pub start: YearMonthDay,
+    #[serde(default = "YearMonthDay::last_day_current_month")]
+    pub end: YearMonthDay,
+}
+
+/// Query parameters for monthly statistics, defaults to the current month
+#[derive(Deserialize, Debug)]
+pub struct MonthlyRange {
+    #[serde(default)]
+    pub start: YearMonth,
+    #[serde(default)]
+    pub end: YearMonth,
+}
+
+/// Query parameters for hourly statistics, defaults to today up to the current hour
+#[derive(Deserialize, Debug)]
+pub struct HourlyRange {
+    #[serde(default = "YearMonthDayHour::first_hour_today")]
+    pub start: YearMonthDayHour,
+    #[serde(default)]
+    pub end: YearMonthDayHour,
+}
+
+/// Query parameters, like axum's `Query` but rejections are JSON errors as well
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) =
+            axum::extract::Query::from_request_parts(parts, state).await?;
+        Ok(Self(value))
+    }
+}
+
+pub enum ApiError {
+    Tuya(TuyaError),
+    Query(QueryRejection),
+}
+
+#[derive(Serialize)]
+struct ApiErrorBody {
+    error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tid: Option<String>,
+}
+
+impl From<TuyaError> for ApiError {
+    fn from(value: TuyaError) -> Self {
+        Self::Tuya(value)
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(value: QueryRejection) -> Self {
+        Self::Query(value)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, body) = match self {
+            Self::Query(rejection) => (
+                rejection.status(),
+                ApiErrorBody {
+                    error: rejection.body_text(),
+                    code: None,
+                    tid: None,
+                },
+            ),
+            Self::Tuya(TuyaError::RequestError { msg, code, tid, .. }) => (
+                StatusCode::BAD_GATEWAY,
+                ApiErrorBody {
+                    error: msg,
+                    code: Some(code),
+                    tid: Some(tid),
+                },
+            ),
+            Self::Tuya(TuyaError::RequestFailure(e)) => (
+                StatusCode::BAD_GATEWAY,
+                ApiErrorBody {
+                    error: e.to_string(),
+                    code: None,
+                    tid: None,
+                },
+            ),
+            Self::Tuya(e @ TuyaError::HostUrlParse(_)) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiErrorBody {
+                    error: e.to_string().trim_end().into(),
+                    code: None,
+                    tid: None,
+                },
+            ),
+        };
+
+        (status, Json(body)).into_response()
+    }
+}
@@ -0,0 +1,64 @@
+use super::{ApiResult, DailyRange, HourlyRange, MonthlyRange, Query, SharedClient};
+use crate::tuya::model::device::{
+    DeviceDailyStatistics, DeviceHourlyStatistics, DeviceInfo, DeviceMonthlyStatistics,
+    DeviceProperties,
+};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+
+pub async fn info(
+    State(client): State<SharedClient>,
+    Path(id): Path<String>,
+) -> ApiResult<DeviceInfo> {
+    let info = client.lock().await.get_device_info(&id).await?;
+    Ok(Json(info))
+}
+
+pub async fn props(
+    State(client): State<SharedClient>,
+    Path(id): Path<String>,
+) -> ApiResult<DeviceProperties> {
+    let props = client.lock().await.get_device_properties(&id).await?;
+    Ok(Json(props))
+}
+
+pub async fn daily_stats(
+    State(client): State<SharedClient>,
+    Path(id): Path<String>,
+    Query(range): Query<DailyRange>,
+) -> ApiResult<DeviceDailyStatistics> {
+    let stats = client
+        .lock()
+        .await
+        .get_daily_device_statistics(&id, &range.start, &range.end)
+        .await?;
+    Ok(Json(stats))
+}
+
+pub async fn monthly_stats(
+    State(client): State<SharedClient>,
+    Path(id): Path<String>,
+    Query(range): Query<MonthlyRange>,
+) -> ApiResult<DeviceMonthlyStatistics> {
+    let stats = client
+        .lock()
+        .await
+        .get_monthly_device_statistics(&id, &range.start, &range.end)
+        .await?;
+    Ok(Json(stats))
+}
+
+pub async fn hourly_stats(
+    State(client): State<SharedClient>,
+    Path(id): Path<String>,
+    Query(range): Query<HourlyRange>,
+) -> ApiResult<DeviceHourlyStatistics> {
+    let stats = client
+        .lock()
+        .await
+        .get_hourly_device_statistics(&id, &range.start, &range.end)
+        .await?;
+    Ok(Json(stats))
+}
@@ -0,0 +1,95 @@
+use super::{ApiResult, DailyRange, HourlyRange, MonthlyRange, Query, SharedClient};
+use crate::tuya::{
+    client::TuyaClient,
+    model::{
+        device::{
+            DeviceDailyStatistics, DeviceHourlyStatistics, DeviceMonthlyStatistics,
+            DevicesResponse, ForDevice,
+        },
+        model::TuyaResult,
+    },
+};
+use axum::{extract::State, Json};
+
+pub async fn list(State(client): State<SharedClient>) -> ApiResult<DevicesResponse> {
+    let devices = client.lock().await.get_devices().await?;
+    Ok(Json(devices))
+}
+
+pub async fn daily_stats(
+    State(client): State<SharedClient>,
+    Query(range): Query<DailyRange>,
+) -> ApiResult<Vec<ForDevice<DeviceDailyStatistics>>> {
+    stats_of_all(&client, &range).await
+}
+
+pub async fn monthly_stats(
+    State(client): State<SharedClient>,
+    Query(range): Query<MonthlyRange>,
+) -> ApiResult<Vec<ForDevice<DeviceMonthlyStatistics>>> {
+    stats_of_all(&client, &range).await
+}
+
+pub async fn hourly_stats(
+    State(client): State<SharedClient>,
+    Query(range): Query<HourlyRange>,
+) -> ApiResult<Vec<ForDevice<DeviceHourlyStatistics>>> {
+    stats_of_all(&client, &range).await
+}
+
+/// A range of statistics that can be fetched for a device
+trait StatsRange {
+    type Stats;
+
+    async fn fetch(&self, client: &mut TuyaClient, id: &str) -> TuyaResult<Self::Stats>;
+}
+
+impl StatsRange for DailyRange {
+    type Stats = DeviceDailyStatistics;
+
+    async fn fetch(&self, client: &mut TuyaClient, id: &str) -> TuyaResult<Self::Stats> {
+        client
+            .get_daily_device_statistics(id, &self.start, &self.end)
+            .await
+    }
+}
+
+impl StatsRange for MonthlyRange {
+    type Stats = DeviceMonthlyStatistics;
+
+    async fn fetch(&self, client: &mut TuyaClient, id: &str) -> TuyaResult<Self::Stats> {
+        client
+            .get_monthly_device_statistics(id, &self.start, &self.end)
+            .await
+    }
+}
+
+impl StatsRange for HourlyRange {
+    type Stats = DeviceHourlyStatistics;
+
+    async fn fetch(&self, client: &mut TuyaClient, id: &str) -> TuyaResult<Self::Stats> {
+        client
+            .get_hourly_device_statistics(id, &self.start, &self.end)
+            .await
+    }
+}
+
+/// Statistics of the range for every device
+async fn stats_of_all<R: StatsRange>(
+    client: &SharedClient,
+    range: &R,
+) -> ApiResult<Vec<ForDevice<R::Stats>>> {
+    let mut client = client.lock().await;
+    let devices = client.get_devices().await?;
+
+    let mut res = Vec::with_capacity(devices.len());
+    for device in devices {
+        let statistics = range.fetch(&mut client, &device.device_id).await?;
+        res.push(ForDevice {
+            device,
+            value: statistics,
+        });
+    }
+
+    Ok(Json(res))
+}
@@ -0,0 +1,109 @@
+use super::{ApiError, SharedClient};
+use crate::tuya::model::device::{DeviceProperties, DeviceResponse};
+use axum::{extract::State, http::header::CONTENT_TYPE, response::IntoResponse};
+use std::fmt::Write;
+
+/// Prometheus metrics of the energy readings of all devices
+pub async fn metrics(State(client): State<SharedClient>) -> Result<impl IntoResponse, ApiError> {
+    let mut client = client.lock().await;
+    let devices = client.get_devices().await?;
+
+    let mut readings = Vec::with_capacity(devices.len());
+    for device in devices {
+        // A single failing device should not break the whole scrape
+        let props = client.get_device_properties(&device.device_id).await.ok();
+        readings.push((device, props));
+    }
+
+    Ok((
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        render(&readings),
+    ))
+}
+
+struct Metric {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    value: fn(&DeviceProperties) -> Option<f64>,
+}
+
+const METRICS: [Metric; 4] = [
+    Metric {
+        name: "tuya_power_watts",
+        kind: "gauge",
+        help: "Current power in W",
+        value: |p| p.power,
+    },
+    Metric {
+        name: "tuya_voltage_volts",
+        kind: "gauge",
+        help: "Current voltage in V",
+        value: |p| p.voltage,
+    },
+    Metric {
+        name: "tuya_current_amperes",
+        kind: "gauge",
+        help: "Current in A",
+        value: |p| p.current,
+    },
+    Metric {
+        // Resets with every report, so not a counter
+        name: "tuya_energy_added_kwh",
+        kind: "gauge",
+        help: "Energy added since the previous report in kWh",
+        value: |p| p.energy,
+    },
+];
+
+fn render(readings: &[(DeviceResponse, Option<DeviceProperties>)]) -> String {
+    let mut out = String::new();
+
+    write_header(
+        &mut out,
+        "tuya_device_online",
+        "gauge",
+        "Whether the device is online",
+    );
+    for (device, _) in readings {
+        write_sample(
+            &mut out,
+            "tuya_device_online",
+            device,
+            device.is_online as u8 as f64,
+        );
+    }
+
+    for metric in METRICS {
+        write_header(&mut out, metric.name, metric.kind, metric.help);
+        for (device, props) in readings {
+            if let Some(v) = props.as_ref().and_then(metric.value) {
+                write_sample(&mut out, metric.name, device, v);
+            }
+        }
+    }
+
+    out
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {name} {help}").expect("Writing to a String does not fail");
+    writeln!(out, "# TYPE {name} {kind}").expect("Writing to a String does not fail");
+}
+
+fn write_sample(out: &mut String, name: &str, device: &DeviceResponse, value: f64) {
+    writeln!(
+        out,
+        "{name}{{device_id=\"{}\",name=\"{}\"}} {value}",
+        escape_label(&device.device_id),
+        escape_label(device.get_name()),
+    )
+    .expect("Writing to a String does not fail");
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
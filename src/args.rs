@@ -1,7 +1,14 @@
-use crate::tuya::model::date::{year_month::YearMonth, year_month_day::YearMonthDay};
+use crate::tuya::model::date::{
+    year_month::YearMonth, year_month_day::YearMonthDay, year_month_day_hour::YearMonthDayHour,
+};
 use clap::{
     builder::{StringValueParser, TypedValueParser},
-    Parser, Subcommand,
+    Parser, Subcommand, ValueEnum,
+};
+use rust_decimal::Decimal;
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
 };
 
 /// Utility for Tuya smart meter devices.
@@ -23,14 +30,44 @@ pub struct Args {
     #[arg(long, env)]
     pub client_secret: String,
 
+    /// Do not read or write the on-disk token cache
+    #[arg(long)]
+    pub no_token_cache: bool,
+
+    /// History database used by `sync`, `get` reads from it for ranges that were synced.
+    /// Defaults to a database in the user's data directory.
+    #[arg(long, env)]
+    pub database: Option<PathBuf>,
+
+    /// Tariff used by `cost` and `forecast` to calculate the price of the consumption.
+    /// Defaults to `tariff.toml` in the user's config directory.
+    #[arg(long, env)]
+    pub tariff: Option<PathBuf>,
+
     /// Set verbosity
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub verbose: u8,
 
+    /// Output format
+    #[arg(long, short, global = true, value_enum, default_value_t = OutputFormat::Pretty)]
+    pub output: OutputFormat,
+
     #[command(subcommand)]
     pub cmd: MainCommands,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable text
+    Pretty,
+    /// A single JSON document
+    Json,
+    /// CSV with one row per device and period (statistics only)
+    Csv,
+    /// CSV with one row per period and one column per device (statistics only)
+    CsvWide,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum MainCommands {
     /// Retrieve device information
@@ -38,16 +75,177 @@ pub enum MainCommands {
         #[command(subcommand)]
         cmd: GetCommands,
     },
+    /// Store statistics of all devices in the history database
+    Sync {
+        #[command(subcommand)]
+        cmd: SyncFrequency,
+    },
+    /// Send commands to devices
+    Set {
+        #[command(subcommand)]
+        cmd: SetCommands,
+    },
+    /// Calculate the electricity cost of the consumption with the tariff
+    Cost {
+        /// Only calculate the cost of this device instead of all devices
+        #[arg(long, short)]
+        id: Option<String>,
+
+        /// Sum the cost per day and tariff band, needs hourly statistics
+        #[arg(long)]
+        by_band: bool,
+
+        #[command(subcommand)]
+        cmd: Frequency,
+    },
+    /// Compare the consumption of two ranges period by period, e.g. with the same months last year
+    Compare {
+        /// Only compare this device instead of all devices
+        #[arg(long, short)]
+        id: Option<String>,
+
+        #[command(subcommand)]
+        cmd: CompareFrequency,
+    },
+    /// Project the consumption at the end of the current month, with its cost when there is a tariff
+    Forecast {
+        /// Only forecast this device instead of all devices
+        #[arg(long, short)]
+        id: Option<String>,
+
+        /// Number of months before this one to learn the weekly pattern from
+        #[arg(long, default_value_t = 0)]
+        history: u32,
+    },
+    /// Find days with an unusual consumption, e.g. a freezer that died or a heater that was left on
+    Anomalies {
+        /// Only check this device instead of all devices
+        #[arg(long, short)]
+        id: Option<String>,
+        /// Start date in the format 'yyyymmdd'
+        #[arg(long, short, default_value_t = YearMonthDay::first_day_current_month(), value_parser = StringValueParser::new().try_map(YearMonthDay::try_from))]
+        start: YearMonthDay,
+        /// End date in the format 'yyyymmdd', today is left out as it isn't over yet
+        #[arg(long, short, default_value_t = YearMonthDay::default(), value_parser = StringValueParser::new().try_map(YearMonthDay::try_from))]
+        end: YearMonthDay,
+        /// Number of days before a day that it is compared to
+        #[arg(long, short, default_value_t = 14)]
+        window: i64,
+        /// Robust z-score from which a day is flagged
+        #[arg(long, short, default_value_t = Decimal::new(35, 1))]
+        threshold: Decimal,
+    },
+    /// Show the power, voltage and current of devices as they change, until Ctrl+C is pressed
+    Watch {
+        /// Only watch this device instead of all devices, can be repeated
+        #[arg(long, short)]
+        id: Vec<String>,
+
+        /// Seconds between readings
+        #[arg(long, short = 'n', default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+    },
+    /// Check alert rules on the devices and send the alerts that fire, until Ctrl+C is pressed
+    Alert {
+        /// Rules and notifiers of the alerts.
+        /// Defaults to `alerts.toml` in the user's config directory.
+        #[arg(long, env)]
+        alerts: Option<PathBuf>,
+
+        /// Seconds between checks
+        #[arg(long, short = 'n', default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+    },
+    /// Publish the readings of all devices to other systems, until Ctrl+C is pressed
+    Publish {
+        #[command(subcommand)]
+        cmd: PublishCommands,
+    },
     /// Serve as an API
     Serve {
+        /// Port to listen on
         #[arg(long, short)]
-        port: u32,
+        port: u16,
+        /// Address to bind to
+        #[arg(long, short, default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+        address: IpAddr,
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum PublishCommands {
+    /// Publish to an MQTT broker
+    Mqtt(MqttArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct MqttArgs {
+    /// Host name of the broker
+    #[arg(long, env = "MQTT_BROKER", default_value = "localhost")]
+    pub broker: String,
+
+    /// Port of the broker, defaults to 1883 or 8883 with TLS
+    #[arg(long, env = "MQTT_PORT")]
+    pub port: Option<u16>,
+
+    #[arg(long, env = "MQTT_USERNAME")]
+    pub username: Option<String>,
+
+    #[arg(long, env = "MQTT_PASSWORD", requires = "username")]
+    pub password: Option<String>,
+
+    /// Connect with TLS, verified with the system's certificates
+    #[arg(long)]
+    pub tls: bool,
+
+    /// PEM certificate of the authority that signed the broker's certificate, implies --tls
+    #[arg(long, env = "MQTT_CA_FILE")]
+    pub ca_file: Option<PathBuf>,
+
+    /// Client ID to connect with, has to be unique on the broker
+    #[arg(long, default_value = "tuya_util")]
+    pub mqtt_client_id: String,
+
+    /// Topic of a JSON document with all readings of a device, an empty string disables it.
+    /// `{device_id}` is replaced by the ID of the device.
+    #[arg(long, default_value = "tuya/{device_id}")]
+    pub topic: String,
+
+    /// Topic of every single reading, an empty string disables it.
+    /// `{device_id}` is replaced by the ID of the device and `{property}` by the name of the reading,
+    /// e.g. `power`, `online` or a property code like `switch_1`.
+    #[arg(long, default_value = "tuya/{device_id}/{property}")]
+    pub property_topic: String,
+
+    /// Topic where this utility publishes `online` and `offline`, also when the connection is lost.
+    /// An empty string disables it.
+    #[arg(long, default_value = "tuya/status")]
+    pub status_topic: String,
+
+    /// Publish Home Assistant discovery configs, so the devices show up with their sensors
+    #[arg(long)]
+    pub discovery: bool,
+
+    /// Topic prefix Home Assistant discovers devices with
+    #[arg(long, default_value = "homeassistant")]
+    pub discovery_prefix: String,
+
+    /// Quality of service of the messages
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=2))]
+    pub qos: u8,
+
+    /// Let the broker keep the last message of every topic for new subscribers
+    #[arg(long)]
+    pub retain: bool,
+
+    /// Seconds between readings
+    #[arg(long, short = 'n', default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum GetCommands {
-    /// Commands for all devices, max 20 devices
+    /// Commands for all devices
     Devices {
         #[command(subcommand)]
         cmd: GetDevicesCommands,
@@ -64,9 +262,9 @@ pub enum GetCommands {
 
 #[derive(Subcommand, Debug)]
 pub enum GetDevicesCommands {
-    /// List all devices (max. 20)
+    /// List all devices
     List,
-    /// Retrieve cumulative energy comsumption in kWh for all devices (max. 20)
+    /// Retrieve cumulative energy comsumption in kWh for all devices
     #[command(subcommand)]
     Stats(Frequency),
 }
@@ -102,4 +300,110 @@ pub enum Frequency {
         #[arg(long, short, default_value_t = YearMonthDay::last_day_current_month(), value_parser = StringValueParser::new().try_map(YearMonthDay::try_from))]
         end: YearMonthDay,
     },
+    /// Hourly frequency
+    Hourly {
+        /// Start hour in the format 'yyyymmddhh'
+        #[arg(long, short, default_value_t = YearMonthDayHour::first_hour_today(), value_parser = StringValueParser::new().try_map(YearMonthDayHour::try_from))]
+        start: YearMonthDayHour,
+        /// End hour in the format 'yyyymmddhh'
+        #[arg(long, short, default_value_t = YearMonthDayHour::default(), value_parser = StringValueParser::new().try_map(YearMonthDayHour::try_from))]
+        end: YearMonthDayHour,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CompareFrequency {
+    /// Monthly frequency
+    Monthly {
+        /// Start date in the format 'yyyymm'
+        #[arg(long, short, default_value_t = YearMonth::default(), value_parser = StringValueParser::new().try_map(YearMonth::try_from))]
+        start: YearMonth,
+        /// End date in the format 'yyyymm'
+        #[arg(long, short, default_value_t = YearMonth::default(), value_parser = StringValueParser::new().try_map(YearMonth::try_from))]
+        end: YearMonth,
+        /// Start date of the range to compare with in the format 'yyyymm', defaults to a year before the start
+        #[arg(long, short, value_parser = StringValueParser::new().try_map(YearMonth::try_from))]
+        base_start: Option<YearMonth>,
+        /// End date of the range to compare with in the format 'yyyymm', defaults to as many months as the range
+        #[arg(long, value_parser = StringValueParser::new().try_map(YearMonth::try_from))]
+        base_end: Option<YearMonth>,
+    },
+    /// Daily frequency
+    Daily {
+        /// Start date in the format 'yyyymmdd'
+        #[arg(long, short, default_value_t = YearMonthDay::first_day_current_month(), value_parser = StringValueParser::new().try_map(YearMonthDay::try_from))]
+        start: YearMonthDay,
+        /// End date in the format 'yyyymmdd'
+        #[arg(long, short, default_value_t = YearMonthDay::default(), value_parser = StringValueParser::new().try_map(YearMonthDay::try_from))]
+        end: YearMonthDay,
+        /// Start date of the range to compare with in the format 'yyyymmdd', defaults to a year before the start
+        #[arg(long, short, value_parser = StringValueParser::new().try_map(YearMonthDay::try_from))]
+        base_start: Option<YearMonthDay>,
+        /// End date of the range to compare with in the format 'yyyymmdd', defaults to as many days as the range
+        #[arg(long, value_parser = StringValueParser::new().try_map(YearMonthDay::try_from))]
+        base_end: Option<YearMonthDay>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SetCommands {
+    /// Commands for a device
+    Device {
+        #[arg(long, short)]
+        id: String,
+
+        #[command(subcommand)]
+        cmd: SetDeviceCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SetDeviceCommands {
+    /// Switch the relay of a smart plug
+    Switch {
+        #[arg(value_enum)]
+        state: SwitchState,
+        /// Property code of the switch
+        #[arg(long, short, default_value = "switch_1")]
+        code: String,
+    },
+    /// Set a property to a value
+    Property {
+        /// Property code, e.g. 'switch_1'
+        code: String,
+        /// New value, parsed as JSON when possible (e.g. 'true' or '10'), otherwise sent as a string
+        value: String,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwitchState {
+    On,
+    Off,
+    /// Invert the current state
+    Toggle,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SyncFrequency {
+    /// Monthly frequency
+    Monthly {
+        /// Start date in the format 'yyyymm'.
+        /// Defaults to the last synced month per device, or 12 months ago when nothing was synced yet.
+        #[arg(long, short, value_parser = StringValueParser::new().try_map(YearMonth::try_from))]
+        start: Option<YearMonth>,
+        /// End date in the format 'yyyymm'
+        #[arg(long, short, default_value_t = YearMonth::default(), value_parser = StringValueParser::new().try_map(YearMonth::try_from))]
+        end: YearMonth,
+    },
+    /// Daily frequency
+    Daily {
+        /// Start date in the format 'yyyymmdd'.
+        /// Defaults to the last synced day per device, or the first day of the month when nothing was synced yet.
+        #[arg(long, short, value_parser = StringValueParser::new().try_map(YearMonthDay::try_from))]
+        start: Option<YearMonthDay>,
+        /// End date in the format 'yyyymmdd'
+        #[arg(long, short, default_value_t = YearMonthDay::default(), value_parser = StringValueParser::new().try_map(YearMonthDay::try_from))]
+        end: YearMonthDay,
+    },
 }
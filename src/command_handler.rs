@@ -1,54 +1,130 @@
 use crate::{
-    args::{Frequency, GetCommands, GetDeviceCommands, GetDevicesCommands},
-    tuya::{client::TuyaClient, model::model::TuyaResult},
-    util::pretty_string::PrettyString,
+    anomaly,
+    args::{
+        CompareFrequency, Frequency, GetCommands, GetDeviceCommands, GetDevicesCommands,
+        OutputFormat, SetCommands, SetDeviceCommands, SwitchState, SyncFrequency,
+    },
+    comparison::{aligned_end, Comparison},
+    error::{AppError, AppResult},
+    forecast::Forecast,
+    store::Store,
+    tariff::{
+        cost::{Cost, Costs},
+        Tariff,
+    },
+    tuya::{
+        client::TuyaClient,
+        model::{
+            date::{year_month::YearMonth, year_month_day::YearMonthDay, DatePeriod},
+            device::{
+                DeviceCommand, DeviceDailyStatistics, DeviceMonthlyStatistics, DeviceResponse,
+                DevicesResponse, ForDevice, Statistics,
+            },
+            energy::Energy,
+        },
+    },
+    util::{csv_export, json::print_json, pretty_string::PrettyString},
+    watch,
 };
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::Value;
+use std::{io, time::Duration};
 
-pub async fn handle_get_commands(args: &GetCommands, client: &mut TuyaClient) -> TuyaResult<()> {
+pub async fn handle_get_commands(
+    args: &GetCommands,
+    client: &mut TuyaClient,
+    store: Option<&Store>,
+    output: OutputFormat,
+) -> AppResult<()> {
     match args {
-        GetCommands::Devices { cmd } => handle_get_devices(client, &cmd).await,
-        GetCommands::Device { id, cmd } => handle_get_device(client, &id, &cmd).await,
+        GetCommands::Devices { cmd } => handle_get_devices(client, store, cmd, output).await,
+        GetCommands::Device { id, cmd } => handle_get_device(client, store, id, cmd, output).await,
     }
 }
 
-async fn handle_get_devices(client: &mut TuyaClient, opt: &GetDevicesCommands) -> TuyaResult<()> {
+async fn handle_get_devices(
+    client: &mut TuyaClient,
+    store: Option<&Store>,
+    opt: &GetDevicesCommands,
+    output: OutputFormat,
+) -> AppResult<()> {
     let devices = client.get_devices().await?;
-    println!("Listing all devices ({}):\n", devices.len());
+    if output == OutputFormat::Pretty {
+        println!("Listing all devices ({}):\n", devices.len());
+    }
 
     match opt {
-        GetDevicesCommands::List => {
-            let max_num_len = (devices.len()).checked_ilog10().unwrap_or(0) + 1;
+        GetDevicesCommands::List => match output {
+            OutputFormat::Pretty => {
+                let max_num_len = (devices.len()).checked_ilog10().unwrap_or(0) + 1;
 
-            for (i, d) in devices.iter().enumerate() {
-                println!(
-                    "[{0}] name: {1}\n{2: >width$} {3}",
-                    i + 1,
-                    d.get_name(),
-                    "id:",
-                    d.device_id,
-                    width = (max_num_len + 3 + 3) as usize,
-                );
+                for (i, d) in devices.iter().enumerate() {
+                    println!(
+                        "[{0}] name: {1}\n{2: >width$} {3}",
+                        i + 1,
+                        d.get_name(),
+                        "id:",
+                        d.device_id,
+                        width = (max_num_len + 3 + 3) as usize,
+                    );
+                }
+                println!();
             }
-            println!();
-        }
+            OutputFormat::Json => print_json(&devices),
+            OutputFormat::Csv | OutputFormat::CsvWide => {
+                let mut w = csv::Writer::from_writer(io::stdout());
+                w.write_record(["device_id", "device_name", "is_online"])?;
+                for d in &devices {
+                    w.write_record([
+                        d.device_id.as_str(),
+                        d.get_name(),
+                        d.is_online.to_string().as_str(),
+                    ])?;
+                }
+                w.flush()?;
+            }
+        },
         GetDevicesCommands::Stats(f) => match f {
             Frequency::Daily { start, end } => {
-                for (i, d) in devices.iter().enumerate() {
-                    let stats = client
-                        .get_daily_device_statistics(d.device_id.as_str(), &start, &end)
-                        .await?;
-
-                    d.print(Some(i), stats);
+                let mut res = Vec::with_capacity(devices.len());
+                for device in devices {
+                    let statistics =
+                        daily_statistics(client, store, &device.device_id, start, end).await?;
+                    res.push(ForDevice {
+                        device,
+                        value: statistics,
+                    });
                 }
+
+                print_device_statistics(&res, output)?;
             }
             Frequency::Monthly { start, end } => {
-                for (i, d) in devices.iter().enumerate() {
-                    let stats = client
-                        .get_monthly_device_statistics(d.device_id.as_str(), &start, &end)
-                        .await?;
+                let mut res = Vec::with_capacity(devices.len());
+                for device in devices {
+                    let statistics =
+                        monthly_statistics(client, store, &device.device_id, start, end).await?;
+                    res.push(ForDevice {
+                        device,
+                        value: statistics,
+                    });
+                }
 
-                    d.print(Some(i), stats)
+                print_device_statistics(&res, output)?;
+            }
+            Frequency::Hourly { start, end } => {
+                let mut res = Vec::with_capacity(devices.len());
+                for device in devices {
+                    let statistics = client
+                        .get_hourly_device_statistics(&device.device_id, start, end)
+                        .await?;
+                    res.push(ForDevice {
+                        device,
+                        value: statistics,
+                    });
                 }
+
+                print_device_statistics(&res, output)?;
             }
         },
     };
@@ -58,37 +134,665 @@ async fn handle_get_devices(client: &mut TuyaClient, opt: &GetDevicesCommands) -
 
 async fn handle_get_device(
     client: &mut TuyaClient,
+    store: Option<&Store>,
     id: &str,
     cmd: &GetDeviceCommands,
-) -> TuyaResult<()> {
+    output: OutputFormat,
+) -> AppResult<()> {
     match cmd {
         GetDeviceCommands::Info => {
             let info = client.get_device_info(id).await?;
-            println!(
-                "Device info: {}",
-                serde_json::to_string_pretty(&info).unwrap()
-            );
+            match output {
+                OutputFormat::Pretty => println!("Device info:\n{}", info.as_pretty_string()),
+                OutputFormat::Json => print_json(&info),
+                OutputFormat::Csv | OutputFormat::CsvWide => return Err(csv_unsupported()),
+            }
         }
         GetDeviceCommands::Props => {
             let props = client.get_device_properties(id).await?;
-            println!(
-                "Device info: {}",
-                serde_json::to_string_pretty(&props).unwrap()
-            );
-        }
-        GetDeviceCommands::Stats(f) => {
-            let stats = match f {
-                Frequency::Daily { start, end } => client
-                    .get_daily_device_statistics(id, &start, &end)
-                    .await?
-                    .as_pretty_string(),
-                Frequency::Monthly { start, end } => client
-                    .get_monthly_device_statistics(id, &start, &end)
-                    .await?
-                    .as_pretty_string(),
+            match output {
+                OutputFormat::Pretty => {
+                    println!("Device properties:\n{}", props.as_pretty_string())
+                }
+                OutputFormat::Json => print_json(&props),
+                OutputFormat::Csv | OutputFormat::CsvWide => return Err(csv_unsupported()),
+            }
+        }
+        GetDeviceCommands::Stats(f) => match f {
+            Frequency::Daily { start, end } => {
+                let stats = daily_statistics(client, store, id, start, end).await?;
+                print_statistics(client, id, stats, output).await?;
+            }
+            Frequency::Monthly { start, end } => {
+                let stats = monthly_statistics(client, store, id, start, end).await?;
+                print_statistics(client, id, stats, output).await?;
+            }
+            Frequency::Hourly { start, end } => {
+                let stats = client.get_hourly_device_statistics(id, start, end).await?;
+                print_statistics(client, id, stats, output).await?;
+            }
+        },
+    };
+    Ok(())
+}
+
+/// Statistics from the store when it has the whole range, from Tuya otherwise
+async fn daily_statistics(
+    client: &mut TuyaClient,
+    store: Option<&Store>,
+    id: &str,
+    start: &YearMonthDay,
+    end: &YearMonthDay,
+) -> AppResult<DeviceDailyStatistics> {
+    if let Some(stats) = store
+        .map(|s| s.daily(id, start, end))
+        .transpose()?
+        .flatten()
+    {
+        return Ok(stats);
+    }
+    Ok(client.get_daily_device_statistics(id, start, end).await?)
+}
+
+/// Statistics from the store when it has the whole range, from Tuya otherwise
+async fn monthly_statistics(
+    client: &mut TuyaClient,
+    store: Option<&Store>,
+    id: &str,
+    start: &YearMonth,
+    end: &YearMonth,
+) -> AppResult<DeviceMonthlyStatistics> {
+    if let Some(stats) = store
+        .map(|s| s.monthly(id, start, end))
+        .transpose()?
+        .flatten()
+    {
+        return Ok(stats);
+    }
+    Ok(client.get_monthly_device_statistics(id, start, end).await?)
+}
+
+pub async fn handle_sync(
+    freq: &SyncFrequency,
+    client: &mut TuyaClient,
+    store: &mut Store,
+    output: OutputFormat,
+) -> AppResult<()> {
+    // Checked first, nothing should be fetched and stored when the command fails anyway
+    reject_csv(output)?;
+
+    let devices = client.get_devices().await?;
+
+    let mut synced = Vec::with_capacity(devices.len());
+    for device in devices {
+        let id = device.device_id.as_str();
+        let count = match freq {
+            SyncFrequency::Daily { start, end } => {
+                // Continue after the last synced day, which is complete,
+                // or at a recent day that Tuya had no value for yet
+                let start = match start {
+                    Some(start) => start.clone(),
+                    None => {
+                        let next = store.last_daily(id)?.and_then(|d| d.next_day());
+                        match (store.first_missing_daily(id)?, next) {
+                            (Some(missing), Some(next)) => missing.min(next),
+                            (missing, next) => missing
+                                .or(next)
+                                .unwrap_or_else(YearMonthDay::first_day_current_month),
+                        }
+                    }
+                };
+
+                // Only days that are over get stored, no need to request the others
+                if start < Store::first_open_day() {
+                    let stats = client.get_daily_device_statistics(id, &start, end).await?;
+                    store.store_daily(id, &start, end, &stats)?
+                } else {
+                    0
+                }
+            }
+            SyncFrequency::Monthly { start, end } => {
+                let start = match start {
+                    Some(start) => start.clone(),
+                    None => {
+                        let next = store.last_monthly(id)?.map(|m| m.next_month().unwrap_or(m));
+                        match (store.first_missing_monthly(id)?, next) {
+                            (Some(missing), Some(next)) => missing.min(next),
+                            (missing, next) => missing.or(next).unwrap_or_else(|| {
+                                let m = YearMonth::default();
+                                YearMonth::new(m.year() - 1, m.month()).unwrap_or(m)
+                            }),
+                        }
+                    }
+                };
+
+                if start < Store::first_open_month() {
+                    let stats = client
+                        .get_monthly_device_statistics(id, &start, end)
+                        .await?;
+                    store.store_monthly(id, &start, end, &stats)?
+                } else {
+                    0
+                }
+            }
+        };
+        synced.push(SyncedDevice { device, count });
+    }
+
+    match output {
+        OutputFormat::Pretty => {
+            let unit = match freq {
+                SyncFrequency::Daily { .. } => "days",
+                SyncFrequency::Monthly { .. } => "months",
             };
-            println!("{}", stats)
+            for (i, s) in synced.iter().enumerate() {
+                println!(
+                    "[{}] {}: {} {} synced",
+                    i + 1,
+                    s.device.get_name(),
+                    s.count,
+                    unit
+                );
+            }
+        }
+        OutputFormat::Json => print_json(&synced),
+        OutputFormat::Csv | OutputFormat::CsvWide => unreachable!("CSV is rejected up front"),
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct SyncedDevice {
+    device: DeviceResponse,
+    /// Number of stored periods
+    count: usize,
+}
+
+pub async fn handle_set_commands(
+    args: &SetCommands,
+    client: &mut TuyaClient,
+    output: OutputFormat,
+) -> AppResult<()> {
+    match args {
+        SetCommands::Device { id, cmd } => handle_set_device(client, id, cmd, output).await,
+    }
+}
+
+async fn handle_set_device(
+    client: &mut TuyaClient,
+    id: &str,
+    cmd: &SetDeviceCommands,
+    output: OutputFormat,
+) -> AppResult<()> {
+    // Checked first, the device shouldn't switch when the command fails anyway
+    reject_csv(output)?;
+
+    let command = match cmd {
+        SetDeviceCommands::Switch { state, code } => {
+            let on = match state {
+                SwitchState::On => true,
+                SwitchState::Off => false,
+                SwitchState::Toggle => {
+                    let props = client.get_device_properties(id).await?;
+                    !props
+                        .get(code)
+                        .and_then(|p| p.value.as_bool())
+                        .ok_or_else(|| {
+                            AppError::InvalidArgument(format!(
+                                "Device has no switch property '{}'",
+                                code
+                            ))
+                        })?
+                }
+            };
+            DeviceCommand {
+                code: code.clone(),
+                value: Value::Bool(on),
+            }
+        }
+        SetDeviceCommands::Property { code, value } => DeviceCommand {
+            code: code.clone(),
+            value: serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.clone())),
+        },
+    };
+
+    let commands = [command];
+    if !client.send_device_commands(id, &commands).await? {
+        return Err(AppError::InvalidArgument(
+            "The device did not accept the command".into(),
+        ));
+    }
+
+    match output {
+        OutputFormat::Pretty => {
+            for c in &commands {
+                println!("Set {} to {}", c.code, c.value);
+            }
+        }
+        OutputFormat::Json => print_json(&commands),
+        OutputFormat::Csv | OutputFormat::CsvWide => unreachable!("CSV is rejected up front"),
+    }
+
+    Ok(())
+}
+
+pub async fn handle_cost(
+    id: Option<&str>,
+    by_band: bool,
+    freq: &Frequency,
+    client: &mut TuyaClient,
+    store: Option<&Store>,
+    tariff: &Tariff,
+    output: OutputFormat,
+) -> AppResult<()> {
+    reject_wide_csv(output, "Costs")?;
+    if by_band && !matches!(freq, Frequency::Hourly { .. }) {
+        return Err(AppError::InvalidArgument(
+            "Splitting the cost by band needs hourly statistics".into(),
+        ));
+    }
+
+    let devices = devices_for(client, id).await?;
+
+    match freq {
+        Frequency::Daily { start, end } => {
+            let costs = device_costs(devices, tariff, async |id| {
+                daily_statistics(client, store, id, start, end).await
+            })
+            .await?;
+            print_costs(&costs, output)
+        }
+        Frequency::Monthly { start, end } => {
+            let costs = device_costs(devices, tariff, async |id| {
+                monthly_statistics(client, store, id, start, end).await
+            })
+            .await?;
+            print_costs(&costs, output)
+        }
+        Frequency::Hourly { start, end } => {
+            let costs = device_costs(devices, tariff, async |id| {
+                Ok(client.get_hourly_device_statistics(id, start, end).await?)
+            })
+            .await?;
+
+            if by_band {
+                let costs = costs
+                    .into_iter()
+                    .map(|c| ForDevice {
+                        value: c.value.by_band(),
+                        device: c.device,
+                    })
+                    .collect::<Vec<_>>();
+                print_costs(&costs, output)
+            } else {
+                print_costs(&costs, output)
+            }
+        }
+    }
+}
+
+/// Costs of every device, `fetch` gets the statistics of a device
+async fn device_costs<S: Statistics>(
+    devices: DevicesResponse,
+    tariff: &Tariff,
+    mut fetch: impl AsyncFnMut(&str) -> AppResult<S>,
+) -> AppResult<Vec<ForDevice<Cost<S::Period>>>> {
+    let mut costs = Vec::with_capacity(devices.len());
+    for device in devices {
+        let stats = fetch(&device.device_id).await?;
+        let cost = tariff.cost(&stats)?;
+        costs.push(ForDevice {
+            device,
+            value: cost,
+        });
+    }
+    Ok(costs)
+}
+
+fn print_costs<C: Costs>(costs: &[ForDevice<C>], output: OutputFormat) -> AppResult<()> {
+    match output {
+        OutputFormat::Pretty => {
+            for (i, c) in costs.iter().enumerate() {
+                c.device.print(Some(i), &c.value);
+            }
+            if let [first, _, ..] = costs {
+                let kwh: Energy = costs.iter().map(|c| c.value.total_kwh()).sum();
+                let cost: Decimal = costs.iter().map(|c| c.value.total_cost()).sum();
+                println!(
+                    "\nAll devices: {}, {:.2} {}",
+                    kwh,
+                    cost,
+                    first.value.currency()
+                );
+            }
+        }
+        OutputFormat::Json => print_json(&costs),
+        OutputFormat::Csv => csv_export::write_rows(costs, io::stdout())?,
+        OutputFormat::CsvWide => unreachable!("wide CSV is rejected up front"),
+    }
+    Ok(())
+}
+
+pub async fn handle_compare(
+    id: Option<&str>,
+    freq: &CompareFrequency,
+    client: &mut TuyaClient,
+    store: Option<&Store>,
+    output: OutputFormat,
+) -> AppResult<()> {
+    reject_wide_csv(output, "Comparisons")?;
+    let devices = devices_for(client, id).await?;
+
+    match freq {
+        CompareFrequency::Monthly {
+            start,
+            end,
+            base_start,
+            base_end,
+        } => {
+            let base_start = base_start.clone().or_else(|| start.previous_year());
+            let comparisons = compare_devices(
+                devices,
+                (start, end),
+                (base_start, base_end.as_ref()),
+                async |id, start, end| monthly_statistics(client, store, id, start, end).await,
+            )
+            .await?;
+            print_comparisons(&comparisons, output)
+        }
+        CompareFrequency::Daily {
+            start,
+            end,
+            base_start,
+            base_end,
+        } => {
+            let base_start = base_start.clone().or_else(|| start.previous_year());
+            let comparisons = compare_devices(
+                devices,
+                (start, end),
+                (base_start, base_end.as_ref()),
+                async |id, start, end| daily_statistics(client, store, id, start, end).await,
+            )
+            .await?;
+            print_comparisons(&comparisons, output)
+        }
+    }
+}
+
+/// Compares the range of every device with the base range, `fetch` gets the statistics of a range.
+///
+/// Without a base end the base range has as many periods as the range.
+async fn compare_devices<P: DatePeriod, S: Statistics<Period = P>>(
+    devices: DevicesResponse,
+    (start, end): (&P, &P),
+    (base_start, base_end): (Option<P>, Option<&P>),
+    mut fetch: impl AsyncFnMut(&str, &P, &P) -> AppResult<S>,
+) -> AppResult<Vec<ForDevice<Comparison<P>>>> {
+    let base_start =
+        base_start.ok_or_else(|| AppError::InvalidArgument("Pass --base-start".into()))?;
+    let base_end = base_end
+        .cloned()
+        .unwrap_or_else(|| aligned_end(start, end, &base_start));
+
+    let mut comparisons = Vec::with_capacity(devices.len());
+    for device in devices {
+        let stats = fetch(&device.device_id, start, end).await?;
+        let base = fetch(&device.device_id, &base_start, &base_end).await?;
+        let comparison = Comparison::new((start, end), (&base_start, &base_end), &stats, &base);
+        comparisons.push(ForDevice {
+            device,
+            value: comparison,
+        });
+    }
+    Ok(comparisons)
+}
+
+pub async fn handle_forecast(
+    id: Option<&str>,
+    history: u32,
+    client: &mut TuyaClient,
+    store: Option<&Store>,
+    tariff: Option<&Tariff>,
+    output: OutputFormat,
+) -> AppResult<()> {
+    reject_wide_csv(output, "Forecasts")?;
+    let today = YearMonthDay::default();
+    let month = today.year_month();
+    if today.day() == 1 && history == 0 {
+        return Err(AppError::InvalidArgument(
+            "No day of this month is over yet, pass --history to forecast from previous months"
+                .into(),
+        ));
+    }
+    let history_start = (0..history).try_fold(month.clone(), |m, _| m.previous_month());
+    let history_end = month.previous_month();
+    if tariff.is_some_and(|t| !t.prices_days(&month)) {
+        eprintln!("The tariff needs hourly statistics, forecasting without costs");
+    }
+
+    let devices = devices_for(client, id).await?;
+
+    let mut forecasts = Vec::with_capacity(devices.len());
+    for device in devices {
+        let id = &device.device_id;
+        let so_far = daily_statistics(client, store, id, &month.first_day(), &today).await?;
+        let history = match (&history_start, &history_end) {
+            (Some(start), Some(end)) if history > 0 => Some(
+                daily_statistics(client, store, id, &start.first_day(), &end.last_day()).await?,
+            ),
+            _ => None,
+        };
+
+        let forecast = Forecast::new(&today, &so_far, history.as_ref(), tariff)?;
+        forecasts.push(ForDevice {
+            device,
+            value: forecast,
+        });
+    }
+
+    match output {
+        OutputFormat::Pretty => {
+            for (i, f) in forecasts.iter().enumerate() {
+                f.device.print(Some(i), &f.value);
+            }
+        }
+        OutputFormat::Json => print_json(&forecasts),
+        OutputFormat::Csv => csv_export::write_rows(&forecasts, io::stdout())?,
+        OutputFormat::CsvWide => unreachable!("wide CSV is rejected up front"),
+    }
+    Ok(())
+}
+
+pub async fn handle_anomalies(
+    id: Option<&str>,
+    (start, end): (&YearMonthDay, &YearMonthDay),
+    window: i64,
+    threshold: Decimal,
+    client: &mut TuyaClient,
+    store: Option<&Store>,
+    output: OutputFormat,
+) -> AppResult<()> {
+    reject_wide_csv(output, "Anomalies")?;
+    if window < 1 {
+        return Err(AppError::InvalidArgument(
+            "The window needs at least one day".into(),
+        ));
+    }
+
+    // Today isn't over yet, it would always look low
+    let today = YearMonthDay::default();
+    let end = match today.days_before(1) {
+        Some(yesterday) if *end >= today => yesterday,
+        _ => end.clone(),
+    };
+    // The first days are compared to the window before them
+    let fetch_start = start.days_before(window).unwrap_or_else(|| start.clone());
+
+    let devices = devices_for(client, id).await?;
+
+    let mut res = Vec::with_capacity(devices.len());
+    for device in devices {
+        let stats = if fetch_start <= end {
+            daily_statistics(client, store, &device.device_id, &fetch_start, &end).await?
+        } else {
+            DeviceDailyStatistics::default()
+        };
+        let anomalies = anomaly::detect(&stats, start, window, threshold);
+        res.push(ForDevice {
+            device,
+            value: anomalies,
+        });
+    }
+
+    match output {
+        OutputFormat::Pretty => {
+            for (i, a) in res.iter().enumerate() {
+                a.device.print(Some(i), &a.value);
+            }
         }
+        OutputFormat::Json => print_json(&res),
+        OutputFormat::Csv => csv_export::write_rows(&res, io::stdout())?,
+        OutputFormat::CsvWide => unreachable!("wide CSV is rejected up front"),
+    }
+    Ok(())
+}
+
+pub async fn handle_watch(
+    ids: &[String],
+    interval: u64,
+    client: &mut TuyaClient,
+    output: OutputFormat,
+) -> AppResult<()> {
+    let devices: DevicesResponse = if ids.is_empty() {
+        client.get_devices().await?
+    } else {
+        let mut devices = Vec::with_capacity(ids.len());
+        for id in ids {
+            devices.push(client.get_device_info(id).await?.into());
+        }
+        devices
     };
+
+    watch::watch(client, devices, Duration::from_secs(interval), output).await
+}
+
+fn print_comparisons<P: DatePeriod>(
+    comparisons: &[ForDevice<Comparison<P>>],
+    output: OutputFormat,
+) -> AppResult<()> {
+    match output {
+        OutputFormat::Pretty => {
+            for (i, c) in comparisons.iter().enumerate() {
+                c.device.print(Some(i), &c.value);
+            }
+        }
+        OutputFormat::Json => print_json(&comparisons),
+        OutputFormat::Csv => csv_export::write_rows(comparisons, io::stdout())?,
+        OutputFormat::CsvWide => unreachable!("wide CSV is rejected up front"),
+    }
     Ok(())
 }
+
+async fn print_statistics<T: Statistics + PrettyString + Serialize>(
+    client: &mut TuyaClient,
+    id: &str,
+    stats: T,
+    output: OutputFormat,
+) -> AppResult<()> {
+    match output {
+        OutputFormat::Pretty => println!("{}", stats.as_pretty_string()),
+        OutputFormat::Json => print_json(&stats),
+        OutputFormat::Csv | OutputFormat::CsvWide => {
+            // CSV rows contain the device name as well
+            let device = client.get_device_info(id).await?.into();
+            let stats = [ForDevice {
+                device,
+                value: stats,
+            }];
+            print_device_statistics(&stats, output)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_device_statistics<T: Statistics + PrettyString + Serialize>(
+    stats: &[ForDevice<T>],
+    output: OutputFormat,
+) -> AppResult<()> {
+    match output {
+        OutputFormat::Pretty => {
+            for (i, s) in stats.iter().enumerate() {
+                s.device.print(Some(i), &s.value);
+                println!();
+            }
+            print_fleet_total(stats);
+        }
+        OutputFormat::Json => print_json(&stats),
+        OutputFormat::Csv => csv_export::write_long(stats, io::stdout())?,
+        OutputFormat::CsvWide => csv_export::write_wide(stats, io::stdout())?,
+    }
+    Ok(())
+}
+
+/// Total of all devices and the share of every device in it
+fn print_fleet_total<T: Statistics>(stats: &[ForDevice<T>]) {
+    let totals = stats
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            // Numbered like the devices above, names aren't unique
+            let label = format!("[{}] {}:", i + 1, s.device.get_name());
+            (label, s.value.total())
+        })
+        .collect::<Vec<_>>();
+    let total: Energy = totals.iter().map(|t| t.1).sum();
+    let padding = totals.iter().map(|t| t.0.len()).max().unwrap_or(0);
+
+    println!("All devices: {}", total);
+    for (label, kwh) in totals {
+        let share = if total == Energy::ZERO {
+            Decimal::ZERO
+        } else {
+            kwh.kwh() / total.kwh() * Decimal::ONE_HUNDRED
+        };
+        println!(
+            "* {: <width$} {} ({:.1}%)",
+            label,
+            kwh,
+            share,
+            width = padding,
+        );
+    }
+}
+
+/// The device with `id`, or all devices without one
+async fn devices_for(client: &mut TuyaClient, id: Option<&str>) -> AppResult<DevicesResponse> {
+    Ok(match id {
+        Some(id) => vec![client.get_device_info(id).await?.into()],
+        None => client.get_devices().await?,
+    })
+}
+
+fn csv_unsupported() -> AppError {
+    AppError::InvalidArgument("CSV output is only supported for device lists and statistics".into())
+}
+
+/// For commands with side effects, so they fail before doing anything
+fn reject_csv(output: OutputFormat) -> AppResult<()> {
+    match output {
+        OutputFormat::Csv | OutputFormat::CsvWide => Err(csv_unsupported()),
+        OutputFormat::Pretty | OutputFormat::Json => Ok(()),
+    }
+}
+
+/// For results without a column per device, checked before anything is requested
+fn reject_wide_csv(output: OutputFormat, results: &str) -> AppResult<()> {
+    match output {
+        OutputFormat::CsvWide => Err(AppError::InvalidArgument(format!(
+            "{} can't be exported as wide CSV, use --output csv",
+            results
+        ))),
+        OutputFormat::Pretty | OutputFormat::Json | OutputFormat::Csv => Ok(()),
+    }
+}
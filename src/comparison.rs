@@ -0,0 +1,178 @@
+use crate::{
+    tuya::model::{
+        date::{periods_while, DatePeriod},
+        device::{DeviceResult, Statistics},
+        energy::Energy,
+    },
+    util::{csv_export::CsvRows, pretty_string::PrettyString},
+};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Consumption of a period next to the period at the same position in the base range
+#[derive(Serialize, Debug)]
+pub struct PeriodComparison<P> {
+    pub period: Option<P>,
+    pub base_period: Option<P>,
+    pub kwh: Option<Energy>,
+    pub base_kwh: Option<Energy>,
+    pub delta_kwh: Option<Energy>,
+    /// Change relative to the base, in percent
+    pub delta_percent: Option<Decimal>,
+}
+
+/// Consumption of a range compared to a base range, period by period
+#[derive(Serialize, Debug)]
+pub struct Comparison<P> {
+    pub periods: Vec<PeriodComparison<P>>,
+    pub total_kwh: Energy,
+    pub base_total_kwh: Energy,
+    pub delta_kwh: Energy,
+    pub delta_percent: Option<Decimal>,
+}
+
+impl<P> DeviceResult for Comparison<P> {
+    const KEY: &'static str = "comparison";
+}
+
+impl<P: DatePeriod> CsvRows for Comparison<P> {
+    const COLUMNS: &'static [&'static str] = &[
+        "period",
+        "base_period",
+        "kwh",
+        "base_kwh",
+        "delta_kwh",
+        "delta_percent",
+    ];
+
+    /// One row per aligned period
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        let cell = |v: Option<String>| v.unwrap_or_default();
+        self.periods
+            .iter()
+            .map(|p| {
+                vec![
+                    cell(p.period.as_ref().map(P::to_string)),
+                    cell(p.base_period.as_ref().map(P::to_string)),
+                    cell(p.kwh.map(|e| e.kwh().to_string())),
+                    cell(p.base_kwh.map(|e| e.kwh().to_string())),
+                    cell(p.delta_kwh.map(|e| e.kwh().to_string())),
+                    cell(p.delta_percent.map(|d| d.to_string())),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl<P: DatePeriod> Comparison<P> {
+    /// Aligns the periods of both ranges by position
+    pub fn new<S: Statistics<Period = P>>(
+        range: (&P, &P),
+        base_range: (&P, &P),
+        stats: &S,
+        base_stats: &S,
+    ) -> Self {
+        let periods = periods_while(range.0, |p, _| p <= range.1);
+        let base_periods = periods_while(base_range.0, |p, _| p <= base_range.1);
+
+        let periods = (0..periods.len().max(base_periods.len()))
+            .map(|i| {
+                let period = periods.get(i).cloned();
+                let base_period = base_periods.get(i).cloned();
+                let kwh = period
+                    .as_ref()
+                    .and_then(|p| stats.periods().get(p))
+                    .copied();
+                let base_kwh = base_period
+                    .as_ref()
+                    .and_then(|p| base_stats.periods().get(p))
+                    .copied();
+                let delta_kwh = kwh.zip(base_kwh).map(|(kwh, base)| kwh - base);
+
+                PeriodComparison {
+                    period,
+                    base_period,
+                    kwh,
+                    base_kwh,
+                    delta_kwh,
+                    delta_percent: delta_kwh.zip(base_kwh).and_then(percent),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let total_kwh = periods.iter().filter_map(|p| p.kwh).sum();
+        let base_total_kwh = periods.iter().filter_map(|p| p.base_kwh).sum();
+        let delta_kwh = total_kwh - base_total_kwh;
+
+        Self {
+            periods,
+            total_kwh,
+            base_total_kwh,
+            delta_kwh,
+            delta_percent: percent((delta_kwh, base_total_kwh)),
+        }
+    }
+}
+
+/// End of the range from `base_start` that is as long as `start..=end`
+pub fn aligned_end<P: DatePeriod>(start: &P, end: &P, base_start: &P) -> P {
+    let len = periods_while(start, |p, _| p <= end).len().max(1);
+    periods_while(base_start, |_, i| i < len)
+        .pop()
+        .unwrap_or_else(|| base_start.clone())
+}
+
+fn percent((delta, base): (Energy, Energy)) -> Option<Decimal> {
+    if base == Energy::ZERO {
+        return None;
+    }
+    Some((delta.kwh() / base.kwh() * Decimal::ONE_HUNDRED).round_dp(1))
+}
+
+impl<P: DatePeriod> PrettyString for Comparison<P> {
+    fn as_pretty_string(&self) -> String {
+        let pretty = |p: &Option<P>| p.as_ref().map_or("-".into(), |p| p.as_pretty_string());
+        let rows = self
+            .periods
+            .iter()
+            .map(|p| {
+                (
+                    format!("{} vs {}:", pretty(&p.period), pretty(&p.base_period)),
+                    p.kwh,
+                    p.base_kwh,
+                    p.delta_kwh,
+                    p.delta_percent,
+                )
+            })
+            .chain([(
+                "Total:".to_string(),
+                Some(self.total_kwh),
+                Some(self.base_total_kwh),
+                Some(self.delta_kwh),
+                self.delta_percent,
+            )])
+            .collect::<Vec<_>>();
+        let padding = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+
+        let energy = |e: Option<Energy>| e.map_or("-".into(), |e| e.to_string());
+        rows.iter()
+            .map(|(label, kwh, base_kwh, delta, percent)| {
+                let mut s = format!(
+                    "* {: <width$} {} vs {}",
+                    label,
+                    energy(*kwh),
+                    energy(*base_kwh),
+                    width = padding,
+                );
+                if let Some(delta) = delta {
+                    s += &format!(", {:+}", delta);
+                }
+                if let Some(percent) = percent {
+                    s += &format!(" ({:+}%)", percent);
+                }
+                s
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
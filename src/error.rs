@@ -0,0 +1,62 @@
+use std::fmt::Display;
+
+use crate::tuya::model::error::TuyaError;
+
+#[derive(Debug)]
+pub enum AppError {
+    Tuya(TuyaError),
+    Io(std::io::Error),
+    Csv(csv::Error),
+    Database(rusqlite::Error),
+    InvalidArgument(String),
+    Config(String),
+    Notify(String),
+    Mqtt(rumqttc::ClientError),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl From<TuyaError> for AppError {
+    fn from(value: TuyaError) -> Self {
+        Self::Tuya(value)
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<csv::Error> for AppError {
+    fn from(value: csv::Error) -> Self {
+        Self::Csv(value)
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(value: rusqlite::Error) -> Self {
+        Self::Database(value)
+    }
+}
+
+impl From<rumqttc::ClientError> for AppError {
+    fn from(value: rumqttc::ClientError) -> Self {
+        Self::Mqtt(value)
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tuya(e) => write!(f, "{}", e),
+            Self::Io(e) => writeln!(f, "IO error: {}", e),
+            Self::Csv(e) => writeln!(f, "Failed to write CSV: {}", e),
+            Self::Database(e) => writeln!(f, "Database error: {}", e),
+            Self::InvalidArgument(msg) => writeln!(f, "{}", msg),
+            Self::Config(msg) => writeln!(f, "{}", msg),
+            Self::Notify(msg) => writeln!(f, "{}", msg),
+            Self::Mqtt(e) => writeln!(f, "MQTT error: {}", e),
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use crate::{
+    error::{AppError, AppResult},
+    tariff::Tariff,
+    tuya::model::{
+        date::{periods_while, year_month::YearMonth, year_month_day::YearMonthDay, DatePeriod},
+        device::{DeviceDailyStatistics, DeviceResult},
+        energy::Energy,
+    },
+    util::{csv_export::CsvRows, pretty_string::PrettyString},
+};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use time::Weekday;
+
+/// The projection is expected to fall within the range 95% of the time
+const CONFIDENCE_Z: f64 = 1.96;
+
+/// Projected consumption at the end of a month
+#[derive(Serialize, Debug)]
+pub struct Forecast {
+    pub month: YearMonth,
+    /// Number of days that are over
+    pub days: usize,
+    pub kwh_so_far: Energy,
+    pub projected_kwh: Energy,
+    /// Lower end of the 95% confidence range
+    pub low_kwh: Energy,
+    /// Upper end of the 95% confidence range
+    pub high_kwh: Energy,
+    /// Only known with a tariff
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<CostForecast>,
+}
+
+/// Projected cost at the end of a month
+#[derive(Serialize, Debug)]
+pub struct CostForecast {
+    pub currency: String,
+    pub cost_so_far: Decimal,
+    pub projected_cost: Decimal,
+    pub low_cost: Decimal,
+    pub high_cost: Decimal,
+}
+
+impl DeviceResult for Forecast {
+    const KEY: &'static str = "forecast";
+}
+
+impl CsvRows for Forecast {
+    const COLUMNS: &'static [&'static str] = &[
+        "month",
+        "days",
+        "kwh_so_far",
+        "projected_kwh",
+        "low_kwh",
+        "high_kwh",
+        "currency",
+        "cost_so_far",
+        "projected_cost",
+        "low_cost",
+        "high_cost",
+    ];
+
+    /// A single row, the cost columns are empty without a tariff
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        let mut row = vec![
+            self.month.to_string(),
+            self.days.to_string(),
+            self.kwh_so_far.kwh().to_string(),
+            self.projected_kwh.kwh().to_string(),
+            self.low_kwh.kwh().to_string(),
+            self.high_kwh.kwh().to_string(),
+        ];
+        match &self.cost {
+            Some(c) => row.extend([
+                c.currency.clone(),
+                c.cost_so_far.to_string(),
+                c.projected_cost.to_string(),
+                c.low_cost.to_string(),
+                c.high_cost.to_string(),
+            ]),
+            None => row.extend(vec![String::new(); 5]),
+        }
+        vec![row]
+    }
+}
+
+impl Forecast {
+    /// Projects the consumption of the days from `today` until the end of the month.
+    ///
+    /// Without history every day left gets the average of the days so far.
+    /// With history the days left get the average of their weekday instead,
+    /// over the history and the days so far, so weekends can differ from workdays.
+    /// The cost is left out when the tariff can't price days.
+    pub fn new(
+        today: &YearMonthDay,
+        so_far: &DeviceDailyStatistics,
+        history: Option<&DeviceDailyStatistics>,
+        tariff: Option<&Tariff>,
+    ) -> AppResult<Self> {
+        let month = today.year_month();
+        let so_far = so_far
+            .days
+            .iter()
+            .filter(|(d, _)| **d < *today && d.year_month() == month)
+            .map(|(d, e)| (d.clone(), *e))
+            .collect::<BTreeMap<_, _>>();
+
+        let samples = history
+            .into_iter()
+            .flat_map(|h| h.days.iter().map(|(d, e)| (d.clone(), *e)))
+            .chain(so_far.clone())
+            .map(|(d, e)| (d.weekday(), e.kwh().to_f64().unwrap_or_default()))
+            .collect::<Vec<_>>();
+        let model = DailyModel::new(&samples, history.is_some());
+
+        let last_day = month.last_day();
+        let days_left = periods_while(today, |d, _| *d <= last_day);
+        let estimates = days_left
+            .iter()
+            .map(|d| model.estimate(d.weekday()))
+            .collect::<Vec<_>>();
+        let estimated: f64 = estimates.iter().sum();
+        let margin = CONFIDENCE_Z * model.std_dev * (days_left.len() as f64).sqrt();
+
+        let kwh_so_far: Energy = so_far.values().sum();
+        let energy = |kwh: f64| {
+            let kwh = Decimal::try_from(kwh.max(0.0)).unwrap_or_default();
+            kwh_so_far + Energy::from_kwh(kwh.round_dp(2))
+        };
+
+        let cost = tariff
+            .filter(|tariff| tariff.prices_days(&month))
+            .map(|tariff| {
+                // Costs of the projections, with the days left scaled to their share of it
+                let cost = |share: f64| -> AppResult<Decimal> {
+                    let mut days = so_far.clone();
+                    for (d, e) in days_left.iter().zip(&estimates) {
+                        let kwh = Decimal::try_from((e * share).max(0.0)).unwrap_or_default();
+                        days.insert(d.clone(), Energy::from_kwh(kwh.round_dp(3)));
+                    }
+                    Ok(tariff.cost(&DeviceDailyStatistics { days })?.total_cost)
+                };
+                let share = |kwh: f64| {
+                    if estimated > 0.0 {
+                        kwh / estimated
+                    } else {
+                        1.0
+                    }
+                };
+
+                Ok::<_, AppError>(CostForecast {
+                    currency: tariff.currency.clone(),
+                    cost_so_far: cost(0.0)?,
+                    projected_cost: cost(1.0)?,
+                    low_cost: cost(share(estimated - margin))?,
+                    high_cost: cost(share(estimated + margin))?,
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            month,
+            days: so_far.len(),
+            kwh_so_far,
+            projected_kwh: energy(estimated),
+            low_kwh: energy(estimated - margin),
+            high_kwh: energy(estimated + margin),
+            cost,
+        })
+    }
+}
+
+/// Expected consumption of a day
+struct DailyModel {
+    mean: f64,
+    /// Average per weekday, when weekdays are told apart
+    weekdays: Option<BTreeMap<u8, f64>>,
+    /// Spread of the days around their expected consumption
+    std_dev: f64,
+}
+
+impl DailyModel {
+    fn new(samples: &[(Weekday, f64)], by_weekday: bool) -> Self {
+        let overall = mean(samples.iter().map(|s| s.1)).unwrap_or_default();
+        let weekdays = by_weekday.then(|| {
+            let mut sums: BTreeMap<u8, Vec<f64>> = BTreeMap::new();
+            for (weekday, kwh) in samples {
+                sums.entry(weekday.number_days_from_monday())
+                    .or_default()
+                    .push(*kwh);
+            }
+            sums.into_iter()
+                .filter_map(|(d, values)| Some((d, mean(values.into_iter())?)))
+                .collect()
+        });
+
+        let mut model = Self {
+            mean: overall,
+            weekdays,
+            std_dev: 0.0,
+        };
+        let deviations = samples
+            .iter()
+            .map(|(weekday, kwh)| (kwh - model.estimate(*weekday)).powi(2));
+        model.std_dev = if samples.len() > 1 {
+            (deviations.sum::<f64>() / (samples.len() - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        model
+    }
+
+    fn estimate(&self, weekday: Weekday) -> f64 {
+        self.weekdays
+            .as_ref()
+            .and_then(|w| w.get(&weekday.number_days_from_monday()))
+            .copied()
+            .unwrap_or(self.mean)
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(s, c), v| (s + v, c + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+impl PrettyString for Forecast {
+    fn as_pretty_string(&self) -> String {
+        let cost = |c: fn(&CostForecast) -> Decimal| {
+            self.cost
+                .as_ref()
+                .map_or(String::new(), |f| format!(", {:.2} {}", c(f), f.currency))
+        };
+        let cost_range = self.cost.as_ref().map_or(String::new(), |f| {
+            format!(", {:.2} - {:.2} {}", f.low_cost, f.high_cost, f.currency)
+        });
+
+        let rows = [
+            (
+                format!("So far ({} days):", self.days),
+                format!("{}{}", self.kwh_so_far, cost(|f| f.cost_so_far)),
+            ),
+            (
+                format!("Projected ({}):", self.month.as_pretty_string()),
+                format!("{}{}", self.projected_kwh, cost(|f| f.projected_cost)),
+            ),
+            (
+                "Range (95%):".to_string(),
+                format!("{} - {}{}", self.low_kwh, self.high_kwh, cost_range),
+            ),
+        ];
+        let padding = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+
+        rows.iter()
+            .map(|(label, value)| format!("* {: <width$} {}", label, value, width = padding))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Month;
+
+    fn kwh(kwh: i64) -> Energy {
+        Energy::from_kwh(Decimal::from(kwh))
+    }
+
+    fn day(day: u8) -> YearMonthDay {
+        YearMonthDay::new(2025, Month::December, day).unwrap()
+    }
+
+    /// 2 kWh on each of the first 10 days of December
+    fn so_far() -> DeviceDailyStatistics {
+        DeviceDailyStatistics {
+            days: (1..=10).map(|d| (day(d), kwh(2))).collect(),
+        }
+    }
+
+    fn tariff(toml: &str) -> Tariff {
+        toml::from_str(toml).expect("valid tariff")
+    }
+
+    #[test]
+    fn days_left_get_the_average_of_the_days_so_far() {
+        let tariff = tariff(
+            r#"
+            currency = "EUR"
+
+            [[price]]
+            type = "flat"
+            price = 0.5
+            "#,
+        );
+        let forecast = Forecast::new(&day(11), &so_far(), None, Some(&tariff)).unwrap();
+
+        assert_eq!(forecast.days, 10);
+        assert_eq!(forecast.kwh_so_far, kwh(20));
+        // 21 days left at 2 kWh, without any spread
+        assert_eq!(forecast.projected_kwh, kwh(62));
+        assert_eq!(forecast.low_kwh, kwh(62));
+
+        let cost = forecast.cost.unwrap();
+        assert_eq!(cost.cost_so_far, Decimal::from(10));
+        assert_eq!(cost.projected_cost, Decimal::from(31));
+    }
+
+    #[test]
+    fn costs_are_left_out_when_the_tariff_needs_hourly_statistics() {
+        let tariff = tariff(
+            r#"
+            currency = "EUR"
+
+            [[price]]
+            type = "day-night"
+            day = 0.34
+            night = 0.25
+            "#,
+        );
+        let forecast = Forecast::new(&day(11), &so_far(), None, Some(&tariff)).unwrap();
+
+        assert_eq!(forecast.projected_kwh, kwh(62));
+        assert!(forecast.cost.is_none());
+    }
+}
@@ -1,30 +1,191 @@
+mod alert;
+mod anomaly;
+mod api;
 mod args;
 mod command_handler;
+mod comparison;
+mod error;
+mod forecast;
+mod mqtt;
+mod store;
+mod tariff;
 mod tuya;
 mod util;
+mod watch;
 
-use crate::args::{Args, MainCommands};
+use crate::args::{Args, MainCommands, PublishCommands};
+use alert::AlertConfig;
 use clap::Parser;
-use command_handler::handle_get_commands;
-use std::process;
-use tuya::client::TuyaClient;
+use command_handler::{
+    handle_anomalies, handle_compare, handle_cost, handle_forecast, handle_get_commands,
+    handle_set_commands, handle_sync, handle_watch,
+};
+use error::{AppError, AppResult};
+use std::{fs, net::SocketAddr, path::PathBuf, process, time::Duration};
+use store::Store;
+use tariff::Tariff;
+use tuya::client::{token_cache::TokenCache, TuyaClient};
 
 #[tokio::main]
-async fn main() -> () {
+async fn main() {
     dotenv::dotenv().ok();
     let args = Args::parse();
 
-    let mut client = TuyaClient::new(&args.host, &args.client_id, &args.client_secret);
+    if let Err(e) = run(args).await {
+        eprintln!("{0}", e);
+        process::exit(1);
+    };
+}
 
-    let res = match args.cmd {
-        MainCommands::Get { cmd } => handle_get_commands(&cmd, &mut client).await,
-        MainCommands::Serve { port: _ } => {
-            todo!("API setup")
+async fn run(args: Args) -> AppResult<()> {
+    let token_cache = TokenCache::for_client(&args.host, &args.client_id);
+    let mut client = match token_cache {
+        Some(cache) if !args.no_token_cache => {
+            TuyaClient::with_token_cache(&args.host, &args.client_id, &args.client_secret, cache)
         }
+        _ => TuyaClient::new(&args.host, &args.client_id, &args.client_secret),
     };
 
-    if let Err(e) = res {
-        eprintln!("{0}", e);
-        process::exit(1);
-    };
+    let database = args.database.or_else(Store::default_path);
+
+    match args.cmd {
+        MainCommands::Get { cmd } => {
+            let store = open_existing_store(database)?;
+            handle_get_commands(&cmd, &mut client, store.as_ref(), args.output).await
+        }
+        MainCommands::Sync { cmd } => {
+            let mut store = open_store(database)?;
+            handle_sync(&cmd, &mut client, &mut store, args.output).await
+        }
+        MainCommands::Set { cmd } => handle_set_commands(&cmd, &mut client, args.output).await,
+        MainCommands::Cost { id, by_band, cmd } => {
+            let tariff = load_tariff(args.tariff)?;
+            let store = open_existing_store(database)?;
+            handle_cost(
+                id.as_deref(),
+                by_band,
+                &cmd,
+                &mut client,
+                store.as_ref(),
+                &tariff,
+                args.output,
+            )
+            .await
+        }
+        MainCommands::Compare { id, cmd } => {
+            let store = open_existing_store(database)?;
+            handle_compare(
+                id.as_deref(),
+                &cmd,
+                &mut client,
+                store.as_ref(),
+                args.output,
+            )
+            .await
+        }
+        MainCommands::Forecast { id, history } => {
+            let tariff = load_optional_tariff(args.tariff)?;
+            let store = open_existing_store(database)?;
+            handle_forecast(
+                id.as_deref(),
+                history,
+                &mut client,
+                store.as_ref(),
+                tariff.as_ref(),
+                args.output,
+            )
+            .await
+        }
+        MainCommands::Anomalies {
+            id,
+            start,
+            end,
+            window,
+            threshold,
+        } => {
+            let store = open_existing_store(database)?;
+            handle_anomalies(
+                id.as_deref(),
+                (&start, &end),
+                window,
+                threshold,
+                &mut client,
+                store.as_ref(),
+                args.output,
+            )
+            .await
+        }
+        MainCommands::Watch { id, interval } => {
+            handle_watch(&id, interval, &mut client, args.output).await
+        }
+        MainCommands::Alert { alerts, interval } => {
+            let config = load_alert_config(alerts)?;
+            alert::monitor(
+                &mut client,
+                config,
+                Duration::from_secs(interval),
+                args.output,
+            )
+            .await
+        }
+        MainCommands::Publish { cmd } => match cmd {
+            PublishCommands::Mqtt(mqtt_args) => mqtt::publish(&mut client, mqtt_args).await,
+        },
+        MainCommands::Serve { port, address } => {
+            api::serve(client, SocketAddr::new(address, port)).await
+        }
+    }
+}
+
+/// Opens the database if it exists, reading never creates it
+fn open_existing_store(path: Option<PathBuf>) -> AppResult<Option<Store>> {
+    match path.filter(|p| p.exists()) {
+        Some(path) => Ok(Some(Store::open(&path)?)),
+        None => Ok(None),
+    }
+}
+
+fn load_tariff(path: Option<PathBuf>) -> AppResult<Tariff> {
+    let path = path.or_else(Tariff::default_path).ok_or_else(|| {
+        AppError::InvalidArgument("No config directory found, pass --tariff".into())
+    })?;
+    if !path.exists() {
+        return Err(AppError::InvalidArgument(format!(
+            "No tariff found at {}, pass --tariff",
+            path.display()
+        )));
+    }
+    Tariff::load(&path)
+}
+
+fn load_alert_config(path: Option<PathBuf>) -> AppResult<AlertConfig> {
+    let path = path.or_else(AlertConfig::default_path).ok_or_else(|| {
+        AppError::InvalidArgument("No config directory found, pass --alerts".into())
+    })?;
+    if !path.exists() {
+        return Err(AppError::InvalidArgument(format!(
+            "No alert config found at {}, pass --alerts",
+            path.display()
+        )));
+    }
+    AlertConfig::load(&path)
+}
+
+/// Tariff from `--tariff`, or the default one if it exists
+fn load_optional_tariff(path: Option<PathBuf>) -> AppResult<Option<Tariff>> {
+    match path.or_else(|| Tariff::default_path().filter(|p| p.exists())) {
+        Some(path) => Ok(Some(Tariff::load(&path)?)),
+        None => Ok(None),
+    }
+}
+
+/// Opens the database, creating it when needed
+fn open_store(path: Option<PathBuf>) -> AppResult<Store> {
+    let path = path.ok_or_else(|| {
+        AppError::InvalidArgument("No data directory found, pass --database".into())
+    })?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(Store::open(&path)?)
 }
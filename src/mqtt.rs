@@ -0,0 +1,231 @@
+pub mod discovery;
+
+use crate::{
+    args::MqttArgs,
+    error::{AppError, AppResult},
+    tuya::{
+        client::TuyaClient,
+        model::device::{DeviceProperties, DeviceResponse},
+    },
+    util::{json::to_json, poll},
+};
+use rumqttc::{
+    AsyncClient, Event, EventLoop, LastWill, MqttOptions, Outgoing, QoS, TlsConfiguration,
+    Transport,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    time::Duration,
+};
+
+/// Time to wait before reconnecting after the connection to the broker failed
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Time to wait for the queued messages to be sent when stopping
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Payloads of the online state of devices and this utility, as Home Assistant expects them
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
+/// Property codes that are published decoded, under the name of their reading
+const DECODED_CODES: [&str; 4] = ["cur_power", "cur_voltage", "cur_current", "add_ele"];
+
+/// All readings of a device, published as JSON on the device topic
+#[derive(Serialize, Debug)]
+struct DeviceState<'a> {
+    name: &'a str,
+    online: bool,
+    /// Current power in W
+    power: Option<f64>,
+    /// Current voltage in V
+    voltage: Option<f64>,
+    /// Current in A
+    current: Option<f64>,
+    /// Energy added since the previous report in kWh
+    energy: Option<f64>,
+    /// Raw values of the other property codes, e.g. `switch_1`
+    properties: BTreeMap<&'a str, &'a Value>,
+}
+
+/// Publishes the readings of all devices every interval until Ctrl+C is pressed.
+///
+/// Connection failures are reported on stderr and retried, so it can run unattended.
+pub async fn publish(client: &mut TuyaClient, args: MqttArgs) -> AppResult<()> {
+    if args.discovery && args.topic.is_empty() && args.property_topic.is_empty() {
+        return Err(AppError::InvalidArgument(
+            "Home Assistant discovery needs --topic or --property-topic".into(),
+        ));
+    }
+
+    let qos = rumqttc::qos(args.qos).expect("QoS is limited to 0-2 by the arguments");
+    let (mqtt, eventloop) = AsyncClient::new(options(&args, qos)?, 100);
+    let connection = tokio::spawn(drive(eventloop));
+    if !args.status_topic.is_empty() {
+        mqtt.publish(&args.status_topic, qos, true, ONLINE).await?;
+    }
+    // Names of the devices whose discovery configs were published, by device ID
+    let mut announced = HashMap::new();
+    poll::every(Duration::from_secs(args.interval), async || {
+        publish_devices(client, &mqtt, &args, qos, &mut announced).await
+    })
+    .await?;
+
+    // Queued messages are sent before the disconnect, a full queue means the broker is unreachable
+    if !args.status_topic.is_empty() {
+        mqtt.try_publish(&args.status_topic, qos, true, OFFLINE)
+            .ok();
+    }
+    if mqtt.try_disconnect().is_ok() {
+        tokio::time::timeout(DISCONNECT_TIMEOUT, connection)
+            .await
+            .ok();
+    }
+    Ok(())
+}
+
+fn options(args: &MqttArgs, qos: QoS) -> AppResult<MqttOptions> {
+    let tls = match &args.ca_file {
+        Some(path) => Some(TlsConfiguration::SimpleNative {
+            ca: fs::read(path)?,
+            client_auth: None,
+        }),
+        None => args.tls.then_some(TlsConfiguration::Native),
+    };
+    let port = args.port.unwrap_or(if tls.is_some() { 8883 } else { 1883 });
+
+    let mut options = MqttOptions::new(&args.mqtt_client_id, &args.broker, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if !args.status_topic.is_empty() {
+        // The broker marks this utility offline when the connection is lost
+        options.set_last_will(LastWill::new(&args.status_topic, OFFLINE, qos, true));
+    }
+    if let Some(tls) = tls {
+        options.set_transport(Transport::tls_with_config(tls));
+    }
+    if let Some(username) = &args.username {
+        options.set_credentials(username, args.password.as_deref().unwrap_or_default());
+    }
+    Ok(options)
+}
+
+/// Handles the network traffic until the client disconnects, the client only queues the messages
+async fn drive(mut eventloop: EventLoop) {
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => return,
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("MQTT connection failed: {}", e);
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+async fn publish_devices(
+    client: &mut TuyaClient,
+    mqtt: &AsyncClient,
+    args: &MqttArgs,
+    qos: QoS,
+    announced: &mut HashMap<String, String>,
+) -> AppResult<()> {
+    let devices = match client.get_devices().await {
+        Ok(devices) => devices,
+        Err(e) => {
+            eprint!("Failed to list the devices: {}", e);
+            return Ok(());
+        }
+    };
+
+    for device in devices {
+        // Offline devices still report their online state
+        let props = match client.get_device_properties(&device.device_id).await {
+            Ok(props) => Some(props),
+            Err(e) => {
+                eprint!("Failed to read {}: {}", device.get_name(), e);
+                None
+            }
+        };
+
+        // Announced again when renamed, so the new name shows up
+        let announced_name = announced.get(&device.device_id).map(String::as_str);
+        if args.discovery && announced_name != Some(device.get_name()) {
+            // Configs are retained, so Home Assistant finds them after a restart
+            for (topic, payload) in discovery::messages(&device, args) {
+                mqtt.publish(topic, qos, true, payload).await?;
+            }
+            announced.insert(device.device_id.clone(), device.get_name().to_string());
+        }
+
+        for (topic, payload) in messages(&device, props.as_ref(), args) {
+            mqtt.publish(topic, qos, args.retain, payload).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Topics and payloads of the readings of a device
+fn messages(
+    device: &DeviceResponse,
+    props: Option<&DeviceProperties>,
+    args: &MqttArgs,
+) -> Vec<(String, String)> {
+    let others = props
+        .iter()
+        .flat_map(|p| &p.properties)
+        .filter(|p| !DECODED_CODES.contains(&p.code.as_str()))
+        .map(|p| (p.code.as_str(), &p.value))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut messages = Vec::new();
+    if !args.topic.is_empty() {
+        let state = DeviceState {
+            name: device.get_name(),
+            online: device.is_online,
+            power: props.and_then(|p| p.power),
+            voltage: props.and_then(|p| p.voltage),
+            current: props.and_then(|p| p.current),
+            energy: props.and_then(|p| p.energy),
+            properties: others.clone(),
+        };
+        messages.push((topic(&args.topic, device, ""), to_json(&state)));
+    }
+
+    if !args.property_topic.is_empty() {
+        let online = if device.is_online { ONLINE } else { OFFLINE };
+        messages.push((topic(&args.property_topic, device, "online"), online.into()));
+
+        let readings = [
+            ("power", props.and_then(|p| p.power)),
+            ("voltage", props.and_then(|p| p.voltage)),
+            ("current", props.and_then(|p| p.current)),
+            ("energy", props.and_then(|p| p.energy)),
+        ];
+        for (name, value) in readings {
+            if let Some(value) = value {
+                messages.push((topic(&args.property_topic, device, name), value.to_string()));
+            }
+        }
+        for (code, value) in others {
+            // Strings are published without quotes
+            let payload = match value {
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            };
+            messages.push((topic(&args.property_topic, device, code), payload));
+        }
+    }
+
+    messages
+}
+
+/// Fills in `{device_id}` and `{property}` of a topic template
+fn topic(template: &str, device: &DeviceResponse, property: &str) -> String {
+    template
+        .replace("{device_id}", &device.device_id)
+        .replace("{property}", property)
+}
@@ -0,0 +1,144 @@
+use super::topic;
+use crate::{args::MqttArgs, tuya::model::device::DeviceResponse, util::json::to_json};
+use serde::Serialize;
+
+/// Sensors of a meter, named after the readings they are published as
+const SENSORS: [Sensor; 4] = [
+    Sensor {
+        property: "power",
+        name: "Power",
+        device_class: Some("power"),
+        unit: "W",
+        state_class: "measurement",
+    },
+    Sensor {
+        property: "voltage",
+        name: "Voltage",
+        device_class: Some("voltage"),
+        unit: "V",
+        state_class: "measurement",
+    },
+    Sensor {
+        property: "current",
+        name: "Current",
+        device_class: Some("current"),
+        unit: "A",
+        state_class: "measurement",
+    },
+    Sensor {
+        property: "energy",
+        name: "Energy added",
+        // Home Assistant only accepts the energy class for totals, `add_ele` resets with every report
+        device_class: None,
+        unit: "kWh",
+        state_class: "measurement",
+    },
+];
+
+struct Sensor {
+    property: &'static str,
+    name: &'static str,
+    device_class: Option<&'static str>,
+    unit: &'static str,
+    state_class: &'static str,
+}
+
+/// Home Assistant MQTT discovery config of a sensor
+/// https://www.home-assistant.io/integrations/sensor.mqtt/
+#[derive(Serialize, Debug)]
+struct SensorConfig<'a> {
+    name: &'a str,
+    unique_id: String,
+    state_topic: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_class: Option<&'a str>,
+    unit_of_measurement: &'a str,
+    state_class: &'a str,
+    /// The sensor is only available when both the device and this utility are online
+    availability: Vec<Availability>,
+    availability_mode: &'a str,
+    device: DeviceConfig<'a>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct Availability {
+    topic: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value_template: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct DeviceConfig<'a> {
+    identifiers: [String; 1],
+    name: &'a str,
+    model: &'a str,
+    manufacturer: &'a str,
+}
+
+/// Topics and payloads of the discovery configs of the sensors of a device.
+///
+/// States are read from the property topics, or from the device topic when those are turned off.
+pub fn messages(device: &DeviceResponse, args: &MqttArgs) -> Vec<(String, String)> {
+    let id = format!("tuya_{}", device.device_id);
+    let by_property = !args.property_topic.is_empty();
+
+    let mut availability = vec![if by_property {
+        Availability {
+            topic: topic(&args.property_topic, device, "online"),
+            value_template: None,
+        }
+    } else {
+        Availability {
+            topic: topic(&args.topic, device, ""),
+            value_template: Some("{{ 'online' if value_json.online else 'offline' }}".into()),
+        }
+    }];
+    if !args.status_topic.is_empty() {
+        availability.push(Availability {
+            topic: args.status_topic.clone(),
+            value_template: None,
+        });
+    }
+
+    SENSORS
+        .iter()
+        .map(|sensor| {
+            let (state_topic, value_template) = if by_property {
+                (topic(&args.property_topic, device, sensor.property), None)
+            } else {
+                (
+                    topic(&args.topic, device, ""),
+                    Some(format!("{{{{ value_json.{} }}}}", sensor.property)),
+                )
+            };
+
+            let config = SensorConfig {
+                name: sensor.name,
+                unique_id: format!("{}_{}", id, sensor.property),
+                state_topic,
+                value_template,
+                device_class: sensor.device_class,
+                unit_of_measurement: sensor.unit,
+                state_class: sensor.state_class,
+                availability: availability.clone(),
+                availability_mode: "all",
+                device: DeviceConfig {
+                    identifiers: [id.clone()],
+                    name: device.get_name(),
+                    model: &device.product_name,
+                    manufacturer: "Tuya",
+                },
+            };
+
+            (
+                format!(
+                    "{}/sensor/{}/{}/config",
+                    args.discovery_prefix, id, sensor.property
+                ),
+                to_json(&config),
+            )
+        })
+        .collect()
+}
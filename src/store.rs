@@ -0,0 +1,258 @@
+use crate::tuya::model::{
+    date::{year_month::YearMonth, year_month_day::YearMonthDay, DatePeriod},
+    device::{DeviceDailyStatistics, DeviceMonthlyStatistics},
+};
+use rusqlite::{params, Connection};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// Days before the first open day whose missing values are requested again on every sync,
+/// as Tuya sometimes computes the statistics of a day late
+const RECHECK_DAYS: i64 = 7;
+
+/// Local history of energy statistics.
+///
+/// Only periods that are over get stored, as their values won't change anymore.
+/// Every such period of a synced range gets a row, with a `NULL` kWh value when Tuya had no value for it.
+/// That way the store knows which ranges are complete and can answer for them instead of Tuya.
+/// Recent `NULL` rows don't count as synced, as Tuya may still fill them in.
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// First day that may not be over yet.
+    ///
+    /// Tuya counts days in the device's time zone, which is up to 14 hours away from UTC,
+    /// so a day is only over everywhere once the next UTC day is over as well.
+    pub fn first_open_day() -> YearMonthDay {
+        let today = YearMonthDay::default();
+        today.days_before(1).unwrap_or(today)
+    }
+
+    /// First month that may not be over yet, the one of the first open day
+    pub fn first_open_month() -> YearMonth {
+        Self::first_open_day().year_month()
+    }
+
+    /// Database in the user's data directory, if there is one
+    pub fn default_path() -> Option<PathBuf> {
+        Some(
+            dirs::data_dir()?
+                .join(env!("CARGO_PKG_NAME"))
+                .join("history.sqlite"),
+        )
+    }
+
+    /// Opens or creates the database
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS daily_statistics (
+                device_id TEXT NOT NULL,
+                day TEXT NOT NULL,
+                kwh TEXT,
+                PRIMARY KEY (device_id, day)
+            );
+            CREATE TABLE IF NOT EXISTS monthly_statistics (
+                device_id TEXT NOT NULL,
+                month TEXT NOT NULL,
+                kwh TEXT,
+                PRIMARY KEY (device_id, month)
+            );",
+        )?;
+
+        Ok(Self { conn })
+    }
+
+    /// Stores the statistics of a synced range, returns the number of stored days
+    pub fn store_daily(
+        &mut self,
+        device_id: &str,
+        start: &YearMonthDay,
+        end: &YearMonthDay,
+        stats: &DeviceDailyStatistics,
+    ) -> rusqlite::Result<usize> {
+        let open = Self::first_open_day();
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO daily_statistics (device_id, day, kwh) VALUES (?1, ?2, ?3)
+                ON CONFLICT (device_id, day) DO UPDATE SET kwh = excluded.kwh",
+            )?;
+
+            let mut day = Some(start.clone());
+            while let Some(d) = day.filter(|d| d <= end && *d < open) {
+                stmt.execute(params![
+                    device_id,
+                    d.as_string(),
+                    stats.days.get(&d).map(|e| e.kwh().to_string())
+                ])?;
+                count += 1;
+                day = d.next_day();
+            }
+        }
+        tx.commit()?;
+
+        Ok(count)
+    }
+
+    /// Stores the statistics of a synced range, returns the number of stored months
+    pub fn store_monthly(
+        &mut self,
+        device_id: &str,
+        start: &YearMonth,
+        end: &YearMonth,
+        stats: &DeviceMonthlyStatistics,
+    ) -> rusqlite::Result<usize> {
+        let open = Self::first_open_month();
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO monthly_statistics (device_id, month, kwh) VALUES (?1, ?2, ?3)
+                ON CONFLICT (device_id, month) DO UPDATE SET kwh = excluded.kwh",
+            )?;
+
+            let mut month = Some(start.clone());
+            while let Some(m) = month.filter(|m| m <= end && *m < open) {
+                stmt.execute(params![
+                    device_id,
+                    m.as_string(),
+                    stats.months.get(&m).map(|e| e.kwh().to_string())
+                ])?;
+                count += 1;
+                month = m.next_month();
+            }
+        }
+        tx.commit()?;
+
+        Ok(count)
+    }
+
+    /// Statistics from the store, if every day of the range was synced
+    pub fn daily(
+        &self,
+        device_id: &str,
+        start: &YearMonthDay,
+        end: &YearMonthDay,
+    ) -> rusqlite::Result<Option<DeviceDailyStatistics>> {
+        let rows = self.query(
+            "SELECT day, kwh FROM daily_statistics
+            WHERE device_id = ?1 AND day >= ?2 AND day <= ?3",
+            device_id,
+            &start.as_string(),
+            &end.as_string(),
+        )?;
+        let recheck = recheck_from().as_string();
+        let synced = rows
+            .iter()
+            .filter(|(day, kwh)| kwh.is_some() || *day < recheck)
+            .count();
+        if (synced as i64) < start.days_until(end) {
+            return Ok(None);
+        }
+
+        let days = rows
+            .into_iter()
+            .filter_map(|(day, kwh)| Some((YearMonthDay::try_from(day).ok()?, kwh?.parse().ok()?)))
+            .collect::<BTreeMap<_, _>>();
+        Ok(Some(DeviceDailyStatistics { days }))
+    }
+
+    /// Statistics from the store, if every month of the range was synced
+    pub fn monthly(
+        &self,
+        device_id: &str,
+        start: &YearMonth,
+        end: &YearMonth,
+    ) -> rusqlite::Result<Option<DeviceMonthlyStatistics>> {
+        let rows = self.query(
+            "SELECT month, kwh FROM monthly_statistics
+            WHERE device_id = ?1 AND month >= ?2 AND month <= ?3",
+            device_id,
+            &start.as_string(),
+            &end.as_string(),
+        )?;
+        let recheck = recheck_from().year_month().as_string();
+        let synced = rows
+            .iter()
+            .filter(|(month, kwh)| kwh.is_some() || *month < recheck)
+            .count();
+        if (synced as i32) < start.months_until(end) {
+            return Ok(None);
+        }
+
+        let months = rows
+            .into_iter()
+            .filter_map(|(month, kwh)| Some((YearMonth::try_from(month).ok()?, kwh?.parse().ok()?)))
+            .collect::<BTreeMap<_, _>>();
+        Ok(Some(DeviceMonthlyStatistics { months }))
+    }
+
+    /// Last synced day of a device
+    pub fn last_daily(&self, device_id: &str) -> rusqlite::Result<Option<YearMonthDay>> {
+        let day: Option<String> = self.conn.query_row(
+            "SELECT MAX(day) FROM daily_statistics WHERE device_id = ?1",
+            params![device_id],
+            |r| r.get(0),
+        )?;
+        Ok(day.and_then(|d| YearMonthDay::try_from(d).ok()))
+    }
+
+    /// First recent day of a device that was synced without a value
+    pub fn first_missing_daily(&self, device_id: &str) -> rusqlite::Result<Option<YearMonthDay>> {
+        let day: Option<String> = self.conn.query_row(
+            "SELECT MIN(day) FROM daily_statistics
+            WHERE device_id = ?1 AND kwh IS NULL AND day >= ?2",
+            params![device_id, recheck_from().as_string()],
+            |r| r.get(0),
+        )?;
+        Ok(day.and_then(|d| YearMonthDay::try_from(d).ok()))
+    }
+
+    /// Last synced month of a device
+    pub fn last_monthly(&self, device_id: &str) -> rusqlite::Result<Option<YearMonth>> {
+        let month: Option<String> = self.conn.query_row(
+            "SELECT MAX(month) FROM monthly_statistics WHERE device_id = ?1",
+            params![device_id],
+            |r| r.get(0),
+        )?;
+        Ok(month.and_then(|m| YearMonth::try_from(m).ok()))
+    }
+
+    /// First recent month of a device that was synced without a value
+    pub fn first_missing_monthly(&self, device_id: &str) -> rusqlite::Result<Option<YearMonth>> {
+        let recheck = recheck_from().year_month();
+        let month: Option<String> = self.conn.query_row(
+            "SELECT MIN(month) FROM monthly_statistics
+            WHERE device_id = ?1 AND kwh IS NULL AND month >= ?2",
+            params![device_id, recheck.as_string()],
+            |r| r.get(0),
+        )?;
+        Ok(month.and_then(|m| YearMonth::try_from(m).ok()))
+    }
+
+    fn query(
+        &self,
+        sql: &str,
+        device_id: &str,
+        start: &str,
+        end: &str,
+    ) -> rusqlite::Result<Vec<(String, Option<String>)>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map(params![device_id, start, end], |r| {
+            Ok((r.get(0)?, r.get(1)?))
+        })?;
+        rows.collect()
+    }
+}
+
+/// Missing values from this day on are requested again
+fn recheck_from() -> YearMonthDay {
+    let day = Store::first_open_day();
+    day.days_before(RECHECK_DAYS).unwrap_or(day)
+}
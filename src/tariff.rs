@@ -0,0 +1,510 @@
+pub mod cost;
+
+use crate::{
+    error::{AppError, AppResult},
+    tuya::model::date::{year_month::YearMonth, year_month_day::YearMonthDay, DatePeriod},
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+use time::Weekday;
+
+/// Electricity prices, read from a TOML file.
+///
+/// ```toml
+/// currency = "EUR"
+///
+/// [[price]]
+/// type = "flat"
+/// price = 0.30
+///
+/// [[price]]
+/// from = "202407"
+/// type = "day-night"
+/// day = 0.34
+/// night = 0.25
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct Tariff {
+    /// Currency the prices are in, e.g. "EUR"
+    pub currency: String,
+    /// Ordered by the month they start in
+    #[serde(rename = "price")]
+    prices: Vec<Price>,
+}
+
+/// A price that applies from a month on, until the next price starts
+#[derive(Deserialize, Debug)]
+pub struct Price {
+    /// First month of the price, without it the price applies to every month before the next one
+    from: Option<YearMonth>,
+    #[serde(flatten)]
+    rate: Rate,
+}
+
+/// Price per kWh
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Rate {
+    /// The same price at any time
+    Flat { price: Decimal },
+    /// Another price at night
+    DayNight {
+        day: Decimal,
+        night: Decimal,
+        /// Hour the night price starts
+        #[serde(default = "default_night_start")]
+        night_start: u8,
+        /// Hour the day price starts again
+        #[serde(default = "default_night_end")]
+        night_end: u8,
+        /// Share of the consumption used at night, between 0 and 1.
+        /// Daily and monthly statistics don't tell when the energy was used, hourly statistics don't need it.
+        night_share: Option<Decimal>,
+    },
+    /// The price goes up with the consumption of the month
+    Tiered { tiers: Vec<Tier> },
+    /// Prices per band of weekdays and hours, needs hourly statistics
+    TimeOfUse {
+        /// The first band that matches an hour sets its price
+        bands: Vec<Band>,
+        /// Days that only match bands for `holiday`, e.g. "20251225"
+        #[serde(default)]
+        holidays: BTreeSet<YearMonthDay>,
+    },
+}
+
+/// Hours of some days that share a price, e.g. peak hours on weekdays
+#[derive(Deserialize, Debug)]
+pub struct Band {
+    name: String,
+    price: Decimal,
+    /// Days the band applies to, every day when missing
+    days: Option<Vec<Day>>,
+    /// First hour of the band
+    #[serde(default)]
+    start: u8,
+    /// Hour the band ends, it wraps around midnight when before `start`
+    #[serde(default = "default_band_end")]
+    end: u8,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+    Holiday,
+}
+
+impl From<Weekday> for Day {
+    fn from(value: Weekday) -> Self {
+        match value {
+            Weekday::Monday => Self::Mon,
+            Weekday::Tuesday => Self::Tue,
+            Weekday::Wednesday => Self::Wed,
+            Weekday::Thursday => Self::Thu,
+            Weekday::Friday => Self::Fri,
+            Weekday::Saturday => Self::Sat,
+            Weekday::Sunday => Self::Sun,
+        }
+    }
+}
+
+/// Price of the consumption up to `up_to` kWh in a month, the last tier has no limit
+#[derive(Deserialize, Debug)]
+pub struct Tier {
+    up_to: Option<Decimal>,
+    price: Decimal,
+}
+
+fn default_night_start() -> u8 {
+    22
+}
+
+fn default_night_end() -> u8 {
+    7
+}
+
+fn default_band_end() -> u8 {
+    24
+}
+
+impl Tariff {
+    /// Tariff in the user's config directory, if there is one
+    pub fn default_path() -> Option<PathBuf> {
+        Some(
+            dirs::config_dir()?
+                .join(env!("CARGO_PKG_NAME"))
+                .join("tariff.toml"),
+        )
+    }
+
+    pub fn load(path: &Path) -> AppResult<Self> {
+        let invalid = |e: &dyn std::fmt::Display| {
+            AppError::Config(format!("Invalid tariff {}: {}", path.display(), e))
+        };
+
+        let s = fs::read_to_string(path)?;
+        let mut tariff: Self = toml::from_str(&s).map_err(|e| invalid(&e))?;
+        tariff.prices.sort_by(|a, b| a.from.cmp(&b.from));
+        tariff.validate().map_err(|e| invalid(&e))?;
+
+        Ok(tariff)
+    }
+
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.prices.is_empty() {
+            return Err("at least one [[price]] is needed");
+        }
+
+        for p in &self.prices {
+            match &p.rate {
+                Rate::Flat { .. } => {}
+                Rate::DayNight {
+                    night_start,
+                    night_end,
+                    night_share,
+                    ..
+                } => {
+                    if *night_start > 23 || *night_end > 23 {
+                        return Err("night hours must be between 0 and 23");
+                    }
+                    if night_share.is_some_and(|s| s < Decimal::ZERO || s > Decimal::ONE) {
+                        return Err("night_share must be between 0 and 1");
+                    }
+                }
+                Rate::Tiered { tiers } => {
+                    let Some((_, limited)) = tiers.split_last() else {
+                        return Err("a tiered price needs at least one tier");
+                    };
+                    // Otherwise the tier would take all consumption and the next ones would never apply
+                    if limited.iter().any(|t| t.up_to.is_none()) {
+                        return Err("every tier but the last needs up_to");
+                    }
+                    let limits = tiers.iter().filter_map(|t| t.up_to);
+                    if limits.clone().zip(limits.skip(1)).any(|(a, b)| a >= b) {
+                        return Err("tiers must be ordered by up_to");
+                    }
+                }
+                Rate::TimeOfUse { bands, .. } => {
+                    if bands.is_empty() {
+                        return Err("a time-of-use price needs at least one band");
+                    }
+                    if bands.iter().any(|b| b.start > 23 || b.end > 24) {
+                        return Err("band hours must be between 0 and 24");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Price that applies to a month
+    pub fn rate(&self, month: &YearMonth) -> AppResult<&Rate> {
+        self.prices
+            .iter()
+            .rev()
+            .find(|p| p.from.as_ref().is_none_or(|from| from <= month))
+            .map(|p| &p.rate)
+            .ok_or_else(|| AppError::Config(format!("The tariff has no price for {}", month)))
+    }
+
+    /// Whether daily statistics of a month can be priced, a missing price is left to `rate`
+    pub fn prices_days(&self, month: &YearMonth) -> bool {
+        !self.rate(month).is_ok_and(Rate::needs_hourly)
+    }
+}
+
+impl Rate {
+    /// Whether the price depends on the hour the energy was used
+    pub fn needs_hourly(&self) -> bool {
+        match self {
+            Self::Flat { .. } | Self::Tiered { .. } => false,
+            Self::DayNight { night_share, .. } => night_share.is_none(),
+            Self::TimeOfUse { .. } => true,
+        }
+    }
+
+    /// Cost of `kwh` used in `period`, when `used` kWh was used earlier that month
+    pub fn cost(
+        &self,
+        period: &impl DatePeriod,
+        used: Decimal,
+        kwh: Decimal,
+    ) -> AppResult<Decimal> {
+        match self {
+            Self::Flat { price } => Ok(kwh * price),
+            Self::DayNight {
+                day,
+                night,
+                night_start,
+                night_end,
+                night_share,
+            } => match period.hour() {
+                Some(hour) if is_between(hour, *night_start, *night_end) => Ok(kwh * night),
+                Some(_) => Ok(kwh * day),
+                None => {
+                    let share = night_share.ok_or_else(|| {
+                        AppError::Config(
+                            "A day/night price needs hourly statistics or a night_share".into(),
+                        )
+                    })?;
+                    Ok(kwh * share * night + kwh * (Decimal::ONE - share) * day)
+                }
+            },
+            Self::Tiered { tiers } => {
+                let mut cost = Decimal::ZERO;
+                let mut used = used;
+                let mut left = kwh;
+
+                for (i, tier) in tiers.iter().enumerate() {
+                    let in_tier = match tier.up_to {
+                        // Whatever is left over after the last tier is charged at its price
+                        Some(up_to) if i < tiers.len() - 1 => {
+                            (up_to - used).max(Decimal::ZERO).min(left)
+                        }
+                        _ => left,
+                    };
+                    cost += in_tier * tier.price;
+                    used += in_tier;
+                    left -= in_tier;
+                    if left <= Decimal::ZERO {
+                        break;
+                    }
+                }
+
+                Ok(cost)
+            }
+            Self::TimeOfUse { bands, holidays } => {
+                let band = Self::time_of_use_band(bands, holidays, period)?;
+                Ok(kwh * band.price)
+            }
+        }
+    }
+
+    /// Name of the band the price of `period` comes from, if the price has bands
+    pub fn band(&self, period: &impl DatePeriod) -> AppResult<Option<&str>> {
+        match self {
+            Self::Flat { .. } | Self::Tiered { .. } => Ok(None),
+            Self::DayNight {
+                night_start,
+                night_end,
+                ..
+            } => Ok(period.hour().map(|hour| {
+                if is_between(hour, *night_start, *night_end) {
+                    "night"
+                } else {
+                    "day"
+                }
+            })),
+            Self::TimeOfUse { bands, holidays } => {
+                let band = Self::time_of_use_band(bands, holidays, period)?;
+                Ok(Some(&band.name))
+            }
+        }
+    }
+
+    fn time_of_use_band<'a>(
+        bands: &'a [Band],
+        holidays: &BTreeSet<YearMonthDay>,
+        period: &impl DatePeriod,
+    ) -> AppResult<&'a Band> {
+        let (Some(day), Some(hour)) = (period.day(), period.hour()) else {
+            return Err(AppError::Config(
+                "A time-of-use price needs hourly statistics".into(),
+            ));
+        };
+        let day_kind = if holidays.contains(&day) {
+            Day::Holiday
+        } else {
+            day.weekday().into()
+        };
+
+        bands
+            .iter()
+            .find(|b| {
+                b.days.as_ref().is_none_or(|days| days.contains(&day_kind))
+                    && is_between(hour, b.start, b.end)
+            })
+            .ok_or_else(|| {
+                AppError::Config(format!(
+                    "No band of the time-of-use price matches {}",
+                    period.as_pretty_string()
+                ))
+            })
+    }
+}
+
+/// Whether `hour` falls in `start..end`, which wraps around midnight when `end` is before `start`
+fn is_between(hour: u8, start: u8, end: u8) -> bool {
+    if start <= end {
+        start <= hour && hour < end
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tuya::model::date::year_month_day_hour::YearMonthDayHour;
+
+    fn tariff(toml: &str) -> Tariff {
+        toml::from_str(toml).expect("valid tariff")
+    }
+
+    fn tiered() -> Tariff {
+        tariff(
+            r#"
+            currency = "EUR"
+
+            [[price]]
+            type = "tiered"
+            tiers = [
+                { up_to = 100, price = 0.20 },
+                { up_to = 200, price = 0.30 },
+                { price = 0.40 },
+            ]
+            "#,
+        )
+    }
+
+    #[test]
+    fn tiered_cost_is_split_over_the_tiers() {
+        let tariff = tiered();
+        let month = YearMonth::try_from("202401").unwrap();
+        let rate = tariff.rate(&month).unwrap();
+
+        // 50 kWh in the first tier, 100 in the second and 50 in the last
+        assert_eq!(
+            rate.cost(&month, Decimal::from(50), Decimal::from(200))
+                .unwrap(),
+            Decimal::from(60)
+        );
+        // The first tier is used up earlier in the month
+        assert_eq!(
+            rate.cost(&month, Decimal::from(150), Decimal::from(10))
+                .unwrap(),
+            Decimal::from(3)
+        );
+        // Beyond the limit of the second tier only the last one applies
+        assert_eq!(
+            rate.cost(&month, Decimal::from(250), Decimal::from(10))
+                .unwrap(),
+            Decimal::from(4)
+        );
+    }
+
+    #[test]
+    fn tiers_before_the_last_need_a_limit() {
+        assert!(tiered().validate().is_ok());
+
+        let tariff = tariff(
+            r#"
+            currency = "EUR"
+
+            [[price]]
+            type = "tiered"
+            tiers = [{ price = 0.20 }, { up_to = 200, price = 0.30 }]
+            "#,
+        );
+        assert_eq!(
+            tariff.validate(),
+            Err("every tier but the last needs up_to")
+        );
+    }
+
+    fn time_of_use() -> Tariff {
+        tariff(
+            r#"
+            currency = "EUR"
+
+            [[price]]
+            type = "time-of-use"
+            holidays = ["20251225"]
+
+            [[price.bands]]
+            name = "weekend"
+            price = 0.22
+            days = ["sat", "sun", "holiday"]
+
+            [[price.bands]]
+            name = "night"
+            price = 0.18
+            start = 23
+            end = 6
+
+            [[price.bands]]
+            name = "peak"
+            price = 0.40
+            start = 7
+            end = 21
+
+            [[price.bands]]
+            name = "off-peak"
+            price = 0.25
+            "#,
+        )
+    }
+
+    fn band(tariff: &Tariff, hour: &str) -> String {
+        let hour = YearMonthDayHour::try_from(hour).unwrap();
+        let rate = tariff.rate(&hour.day().year_month()).unwrap();
+        rate.band(&hour).unwrap().unwrap().to_string()
+    }
+
+    #[test]
+    fn time_of_use_takes_the_first_matching_band() {
+        let tariff = time_of_use();
+
+        // Tuesday
+        assert_eq!(band(&tariff, "2025122308"), "peak");
+        assert_eq!(band(&tariff, "2025122321"), "off-peak");
+        // The night band wraps around midnight
+        assert_eq!(band(&tariff, "2025122323"), "night");
+        assert_eq!(band(&tariff, "2025122405"), "night");
+        assert_eq!(band(&tariff, "2025122406"), "off-peak");
+        // Saturday, the weekend band comes first
+        assert_eq!(band(&tariff, "2025122708"), "weekend");
+        assert_eq!(band(&tariff, "2025122723"), "weekend");
+
+        let hour = YearMonthDayHour::try_from("2025122308").unwrap();
+        let rate = tariff.rate(&hour.day().year_month()).unwrap();
+        assert_eq!(
+            rate.cost(&hour, Decimal::ZERO, Decimal::from(10)).unwrap(),
+            Decimal::from(4)
+        );
+    }
+
+    #[test]
+    fn holidays_only_match_holiday_bands() {
+        let tariff = time_of_use();
+
+        // A Thursday, but a holiday
+        assert_eq!(band(&tariff, "2025122508"), "weekend");
+        assert_eq!(band(&tariff, "2025122608"), "peak");
+    }
+
+    #[test]
+    fn time_of_use_needs_hourly_statistics() {
+        let tariff = time_of_use();
+        let day = YearMonthDay::try_from("20251223").unwrap();
+        let rate = tariff.rate(&day.year_month()).unwrap();
+
+        assert!(rate.band(&day).is_err());
+        assert!(rate.cost(&day, Decimal::ZERO, Decimal::ONE).is_err());
+        assert!(!tariff.prices_days(&day.year_month()));
+        assert!(tiered().prices_days(&day.year_month()));
+    }
+}
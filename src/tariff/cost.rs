@@ -0,0 +1,297 @@
+use super::Tariff;
+use crate::{
+    error::AppResult,
+    tuya::model::{
+        date::{year_month_day::YearMonthDay, year_month_day_hour::YearMonthDayHour, DatePeriod},
+        device::{DeviceResult, Statistics},
+        energy::Energy,
+    },
+    util::{csv_export::CsvRows, pretty_string::PrettyString},
+};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Band name of consumption whose price has no bands
+const STANDARD_BAND: &str = "standard";
+
+/// Costs of a device, printable as text, JSON and CSV
+pub trait Costs: PrettyString + Serialize + DeviceResult + CsvRows {
+    fn currency(&self) -> &str;
+
+    fn total_kwh(&self) -> Energy;
+
+    fn total_cost(&self) -> Decimal;
+}
+
+/// Columns of both kinds of costs
+const COST_COLUMNS: &[&str] = &["period", "band", "kwh", "cost", "currency"];
+
+/// Consumption and cost of a single period
+#[derive(Serialize, Debug)]
+pub struct PeriodCost<P> {
+    pub period: P,
+    /// Tariff band the price comes from, if the price has bands
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub band: Option<String>,
+    pub kwh: Energy,
+    pub cost: Decimal,
+}
+
+/// Statistics with the cost of every period
+#[derive(Serialize, Debug)]
+pub struct Cost<P> {
+    pub currency: String,
+    pub periods: Vec<PeriodCost<P>>,
+    pub total_kwh: Energy,
+    pub total_cost: Decimal,
+}
+
+/// Consumption and cost of a tariff band
+#[derive(Serialize, Debug)]
+pub struct BandCost {
+    pub band: String,
+    pub kwh: Energy,
+    pub cost: Decimal,
+}
+
+/// Costs of a single day, split by tariff band
+#[derive(Serialize, Debug)]
+pub struct DayBandCosts {
+    pub day: YearMonthDay,
+    pub bands: Vec<BandCost>,
+}
+
+/// Costs split by tariff band per day, with the totals per band
+#[derive(Serialize, Debug)]
+pub struct BandCosts {
+    pub currency: String,
+    pub days: Vec<DayBandCosts>,
+    pub bands: Vec<BandCost>,
+    pub total_kwh: Energy,
+    pub total_cost: Decimal,
+}
+
+impl Tariff {
+    /// Applies the tariff to every period of the statistics.
+    /// Tiers count the consumption from the start of the month, or from the first period when it starts later.
+    pub fn cost<S: Statistics>(&self, stats: &S) -> AppResult<Cost<S::Period>> {
+        let mut periods = Vec::new();
+        let mut month = None;
+        let mut used = Decimal::ZERO;
+
+        for (period, kwh) in stats.periods() {
+            let kwh = *kwh;
+            let m = period.year_month();
+            if month.as_ref() != Some(&m) {
+                used = Decimal::ZERO;
+            }
+
+            let rate = self.rate(&m)?;
+            let cost = rate.cost(period, used, kwh.kwh())?;
+            let band = rate.band(period)?.map(String::from);
+            used += kwh.kwh();
+            month = Some(m);
+            periods.push(PeriodCost {
+                period: period.clone(),
+                band,
+                kwh,
+                cost,
+            });
+        }
+
+        Ok(Cost {
+            currency: self.currency.clone(),
+            total_kwh: periods.iter().map(|p| p.kwh).sum(),
+            total_cost: periods.iter().map(|p| p.cost).sum(),
+            periods,
+        })
+    }
+}
+
+impl Cost<YearMonthDayHour> {
+    /// Sums the hours per day and tariff band
+    pub fn by_band(&self) -> BandCosts {
+        let mut days: BTreeMap<YearMonthDay, BTreeMap<&str, (Energy, Decimal)>> = BTreeMap::new();
+        let mut bands: BTreeMap<&str, (Energy, Decimal)> = BTreeMap::new();
+
+        for p in &self.periods {
+            let band = p.band.as_deref().unwrap_or(STANDARD_BAND);
+            for sum in [
+                days.entry(p.period.day())
+                    .or_default()
+                    .entry(band)
+                    .or_default(),
+                bands.entry(band).or_default(),
+            ] {
+                sum.0 += p.kwh;
+                sum.1 += p.cost;
+            }
+        }
+
+        BandCosts {
+            currency: self.currency.clone(),
+            days: days
+                .into_iter()
+                .map(|(day, bands)| DayBandCosts {
+                    day,
+                    bands: band_costs(bands),
+                })
+                .collect(),
+            bands: band_costs(bands),
+            total_kwh: self.total_kwh,
+            total_cost: self.total_cost,
+        }
+    }
+}
+
+fn band_costs(sums: BTreeMap<&str, (Energy, Decimal)>) -> Vec<BandCost> {
+    sums.into_iter()
+        .map(|(band, (kwh, cost))| BandCost {
+            band: band.into(),
+            kwh,
+            cost,
+        })
+        .collect()
+}
+
+impl<P> DeviceResult for Cost<P> {
+    const KEY: &'static str = "cost";
+}
+
+impl DeviceResult for BandCosts {
+    const KEY: &'static str = "cost";
+}
+
+impl<P: DatePeriod> Costs for Cost<P> {
+    fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    fn total_kwh(&self) -> Energy {
+        self.total_kwh
+    }
+
+    fn total_cost(&self) -> Decimal {
+        self.total_cost
+    }
+}
+
+impl<P: DatePeriod> CsvRows for Cost<P> {
+    const COLUMNS: &'static [&'static str] = COST_COLUMNS;
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.periods
+            .iter()
+            .map(|p| {
+                vec![
+                    p.period.to_string(),
+                    p.band.clone().unwrap_or_default(),
+                    p.kwh.kwh().to_string(),
+                    p.cost.to_string(),
+                    self.currency.clone(),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl Costs for BandCosts {
+    fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    fn total_kwh(&self) -> Energy {
+        self.total_kwh
+    }
+
+    fn total_cost(&self) -> Decimal {
+        self.total_cost
+    }
+}
+
+impl CsvRows for BandCosts {
+    const COLUMNS: &'static [&'static str] = COST_COLUMNS;
+
+    /// One row per day and band
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.days
+            .iter()
+            .flat_map(|d| {
+                d.bands.iter().map(|b| {
+                    vec![
+                        d.day.to_string(),
+                        b.band.clone(),
+                        b.kwh.kwh().to_string(),
+                        b.cost.to_string(),
+                        self.currency.clone(),
+                    ]
+                })
+            })
+            .collect()
+    }
+}
+
+impl<P: DatePeriod> PrettyString for Cost<P> {
+    fn as_pretty_string(&self) -> String {
+        let rows = self
+            .periods
+            .iter()
+            .map(|p| match &p.band {
+                Some(band) => (
+                    format!("{} ({}):", p.period.as_pretty_string(), band),
+                    p.kwh,
+                    p.cost,
+                ),
+                None => (p.period.as_pretty_string() + ":", p.kwh, p.cost),
+            })
+            .chain([("Total:".to_string(), self.total_kwh, self.total_cost)])
+            .collect::<Vec<_>>();
+
+        pretty_rows(&rows, &self.currency)
+    }
+}
+
+impl PrettyString for BandCosts {
+    fn as_pretty_string(&self) -> String {
+        let rows = self
+            .days
+            .iter()
+            .flat_map(|d| {
+                d.bands.iter().map(|b| {
+                    (
+                        format!("{} ({}):", d.day.as_pretty_string(), b.band),
+                        b.kwh,
+                        b.cost,
+                    )
+                })
+            })
+            .chain(
+                self.bands
+                    .iter()
+                    .map(|b| (format!("Total ({}):", b.band), b.kwh, b.cost)),
+            )
+            .chain([("Total:".to_string(), self.total_kwh, self.total_cost)])
+            .collect::<Vec<_>>();
+
+        pretty_rows(&rows, &self.currency)
+    }
+}
+
+fn pretty_rows(rows: &[(String, Energy, Decimal)], currency: &str) -> String {
+    let padding = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+
+    rows.iter()
+        .map(|(label, kwh, cost)| {
+            format!(
+                "* {: <width$} {}, {:.2} {}",
+                label,
+                kwh,
+                cost,
+                currency,
+                width = padding,
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
@@ -1,8 +1,12 @@
+use crate::tuya::model::device::ThingModel;
 use reqwest::Client;
+use std::collections::HashMap;
+use token_cache::TokenCache;
 
 pub mod auth;
 pub mod device;
 pub mod request;
+pub mod token_cache;
 
 pub struct TuyaClient {
     host: String,
@@ -11,6 +15,9 @@ pub struct TuyaClient {
     access_token: Option<String>,
     refresh_token: Option<String>,
     expiration_time: Option<u128>,
+    token_cache: Option<TokenCache>,
+    /// By device ID
+    thing_models: HashMap<String, ThingModel>,
     request_client: Client,
 }
 
@@ -23,7 +30,26 @@ impl TuyaClient {
             access_token: None,
             refresh_token: None,
             expiration_time: None,
+            token_cache: None,
+            thing_models: HashMap::new(),
             request_client: Client::new(),
         }
     }
+
+    /// Creates a client that starts from the cached tokens and updates the cache after every refresh
+    pub fn with_token_cache(
+        host: &str,
+        client_id: &str,
+        client_secret: &str,
+        token_cache: TokenCache,
+    ) -> Self {
+        let mut client = Self::new(host, client_id, client_secret);
+        if let Some(token) = token_cache.load() {
+            client.access_token = Some(token.access_token);
+            client.refresh_token = Some(token.refresh_token);
+            client.expiration_time = Some(token.expiration_time);
+        }
+        client.token_cache = Some(token_cache);
+        client
+    }
 }
@@ -1,10 +1,17 @@
 use super::TuyaClient;
 use crate::{
-    tuya::model::{auth::TokenResponse, model::TuyaResult},
+    tuya::model::{
+        auth::{CachedToken, TokenResponse},
+        error::TuyaError,
+        model::TuyaResult,
+    },
     util::time::get_time,
 };
 use reqwest::Method;
 
+/// Refresh a bit before the token actually expires, in milliseconds
+const EXPIRATION_MARGIN: u128 = 60 * 1000;
+
 impl TuyaClient {
     pub async fn get_access_token(&mut self) -> TuyaResult<()> {
         let current_time = get_time();
@@ -17,25 +24,71 @@ impl TuyaClient {
             }
         }
 
-        let res: TokenResponse;
-        if let Some(token) = &self.refresh_token {
-            res = self
+        let res: TokenResponse = if let Some(token) = self.refresh_token.clone() {
+            match self
                 .make_request_token(
                     Method::GET,
                     format!("/v1.0/token/{0}", token).as_str(),
                     Some(&[("grant_type", "1")]),
                 )
-                .await?;
+                .await
+            {
+                Ok(res) => res,
+                // The refresh token can be expired as well, e.g. when it was loaded from the cache
+                Err(TuyaError::RequestError { .. }) => self.request_new_token().await?,
+                Err(e) => return Err(e),
+            }
         } else {
-            res = self
-                .make_request_token(Method::GET, "/v1.0/token", Some(&[("grant_type", "1")]))
-                .await?;
-        }
+            self.request_new_token().await?
+        };
+
+        // `expire_time` is in seconds
+        let expiration_time =
+            current_time + (res.expire_time * 1000).saturating_sub(EXPIRATION_MARGIN);
 
         self.access_token = Some(res.access_token);
         self.refresh_token = Some(res.refresh_token);
-        self.expiration_time = Some(current_time + res.expire_time);
+        self.expiration_time = Some(expiration_time);
+        self.store_token();
 
         Ok(())
     }
+
+    /// Forgets the current tokens, the next request will retrieve new ones
+    pub fn invalidate_access_token(&mut self) {
+        self.access_token = None;
+        self.refresh_token = None;
+        self.expiration_time = None;
+    }
+
+    async fn request_new_token(&mut self) -> TuyaResult<TokenResponse> {
+        self.make_request_token(Method::GET, "/v1.0/token", Some(&[("grant_type", "1")]))
+            .await
+    }
+
+    fn store_token(&self) {
+        let (Some(cache), Some(access_token), Some(refresh_token), Some(expiration_time)) = (
+            &self.token_cache,
+            &self.access_token,
+            &self.refresh_token,
+            self.expiration_time,
+        ) else {
+            return;
+        };
+
+        let token = CachedToken {
+            access_token: access_token.clone(),
+            refresh_token: refresh_token.clone(),
+            expiration_time,
+        };
+
+        // Not fatal, the next invocation simply retrieves a new token
+        if let Err(e) = cache.store(&token) {
+            eprintln!(
+                "Failed to write token cache {}: {}",
+                cache.path().display(),
+                e
+            );
+        }
+    }
 }
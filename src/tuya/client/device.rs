@@ -1,83 +1,208 @@
 use super::TuyaClient;
 use crate::tuya::model::{
-    date::{year_month::YearMonth, year_month_day::YearMonthDay},
-    device::{DeviceDailyStatistics, DeviceMonthlyStatistics, DevicesResponse},
+    date::{
+        year_month::YearMonth, year_month_day::YearMonthDay, year_month_day_hour::YearMonthDayHour,
+    },
+    device::{
+        DeviceCommand, DeviceDailyStatistics, DeviceHourlyStatistics, DeviceInfo,
+        DeviceMonthlyStatistics, DeviceProperties, DeviceShadow, DevicesResponse, ThingModel,
+    },
     model::TuyaResult,
 };
 use reqwest::Method;
-use serde_json::Value;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Maximum page size allowed by Tuya
+const DEVICES_PAGE_SIZE: usize = 20;
+/// Longest range of days Tuya accepts in a single statistics request
+const MAX_DAYS_PER_REQUEST: i64 = 31;
+/// Longest range of months Tuya accepts in a single statistics request
+const MAX_MONTHS_PER_REQUEST: usize = 12;
+/// Longest range of hours Tuya accepts in a single statistics request
+const MAX_HOURS_PER_REQUEST: i64 = 24;
 
 impl TuyaClient {
-    pub async fn get_device_info(&mut self, device_id: &str) -> TuyaResult<Value> {
+    pub async fn get_device_info(&mut self, device_id: &str) -> TuyaResult<DeviceInfo> {
         self.make_request_business(
             Method::GET,
             format!("/v2.0/cloud/thing/{device_id}").as_str(),
             None,
+            None,
         )
         .await
     }
 
-    pub async fn get_device_properties(&mut self, device_id: &str) -> TuyaResult<Value> {
+    /// Properties with the metering codes decoded, see [`TuyaClient::get_thing_model`]
+    pub async fn get_device_properties(&mut self, device_id: &str) -> TuyaResult<DeviceProperties> {
+        let model = self.get_thing_model(device_id).await?;
+        let shadow: DeviceShadow = self
+            .make_request_business(
+                Method::GET,
+                format!("/v2.0/cloud/thing/{device_id}/shadow/properties").as_str(),
+                None,
+                None,
+            )
+            .await?;
+        Ok(DeviceProperties::new(shadow.properties, &model))
+    }
+
+    /// Scales of the property codes, requested once per device since they are set per product
+    pub async fn get_thing_model(&mut self, device_id: &str) -> TuyaResult<ThingModel> {
+        if let Some(model) = self.thing_models.get(device_id) {
+            return Ok(model.clone());
+        }
+
+        let model: ThingModel = self
+            .make_request_business(
+                Method::GET,
+                format!("/v2.0/cloud/thing/{device_id}/model").as_str(),
+                None,
+                None,
+            )
+            .await?;
+        self.thing_models
+            .insert(device_id.to_string(), model.clone());
+        Ok(model)
+    }
+
+    /// Returns whether the device accepted the commands
+    pub async fn send_device_commands(
+        &mut self,
+        device_id: &str,
+        commands: &[DeviceCommand],
+    ) -> TuyaResult<bool> {
         self.make_request_business(
-            Method::GET,
-            format!("/v2.0/cloud/thing/{device_id}/shadow/properties").as_str(),
+            Method::POST,
+            format!("/v1.0/iot-03/devices/{device_id}/commands").as_str(),
             None,
+            Some(&json!({ "commands": commands })),
         )
         .await
     }
 
+    /// Long ranges are split into multiple requests, the results are merged
     pub async fn get_monthly_device_statistics(
         &mut self,
         device_id: &str,
         start: &YearMonth,
         end: &YearMonth,
     ) -> TuyaResult<DeviceMonthlyStatistics> {
-        let start: String = start.as_string();
-        let end: String = end.as_string();
+        let mut months = BTreeMap::new();
 
-        let res = self
-            .make_request_business(
-                Method::GET,
-                format!("/v1.0/devices/{device_id}/statistics/months").as_str(),
-                Some(&[
-                    ("code", "add_ele"),
-                    ("end_month", &end),
-                    ("start_month", &start),
-                ]),
-            )
-            .await;
-        res
+        for (start, end) in YearMonth::split_range(start, end, MAX_MONTHS_PER_REQUEST) {
+            let start: String = start.as_string();
+            let end: String = end.as_string();
+
+            let res: DeviceMonthlyStatistics = self
+                .make_request_business(
+                    Method::GET,
+                    format!("/v1.0/devices/{device_id}/statistics/months").as_str(),
+                    Some(&[
+                        ("code", "add_ele"),
+                        ("end_month", &end),
+                        ("start_month", &start),
+                    ]),
+                    None,
+                )
+                .await?;
+            months.extend(res.months);
+        }
+
+        Ok(DeviceMonthlyStatistics { months })
     }
 
+    /// Long ranges are split into multiple requests, the results are merged
     pub async fn get_daily_device_statistics(
         &mut self,
         device_id: &str,
         start: &YearMonthDay,
         end: &YearMonthDay,
     ) -> TuyaResult<DeviceDailyStatistics> {
-        let start: String = start.as_string();
-        let end: String = end.as_string();
+        let mut days = BTreeMap::new();
 
-        let res = self
-            .make_request_business(
-                Method::GET,
-                format!("/v1.0/devices/{device_id}/statistics/days").as_str(),
-                Some(&[
-                    ("start_day", &start),
-                    ("end_day", &end),
-                    ("code", "add_ele"),
-                ]),
-            )
-            .await;
-        res
+        for (start, end) in YearMonthDay::split_range(start, end, MAX_DAYS_PER_REQUEST) {
+            let start: String = start.as_string();
+            let end: String = end.as_string();
+
+            let res: DeviceDailyStatistics = self
+                .make_request_business(
+                    Method::GET,
+                    format!("/v1.0/devices/{device_id}/statistics/days").as_str(),
+                    Some(&[
+                        ("start_day", &start),
+                        ("end_day", &end),
+                        ("code", "add_ele"),
+                    ]),
+                    None,
+                )
+                .await?;
+            days.extend(res.days);
+        }
+
+        Ok(DeviceDailyStatistics { days })
     }
 
+    /// Long ranges are split into multiple requests, the results are merged
+    pub async fn get_hourly_device_statistics(
+        &mut self,
+        device_id: &str,
+        start: &YearMonthDayHour,
+        end: &YearMonthDayHour,
+    ) -> TuyaResult<DeviceHourlyStatistics> {
+        let mut hours = BTreeMap::new();
+
+        for (start, end) in YearMonthDayHour::split_range(start, end, MAX_HOURS_PER_REQUEST) {
+            let start: String = start.as_string();
+            let end: String = end.as_string();
+
+            let res: DeviceHourlyStatistics = self
+                .make_request_business(
+                    Method::GET,
+                    format!("/v1.0/devices/{device_id}/statistics/hours").as_str(),
+                    Some(&[
+                        ("start_hour", &start),
+                        ("end_hour", &end),
+                        ("code", "add_ele"),
+                    ]),
+                    None,
+                )
+                .await?;
+            hours.extend(res.hours);
+        }
+
+        Ok(DeviceHourlyStatistics { hours })
+    }
+
+    /// Retrieves all devices, following the pages of `/v2.0/cloud/thing/device`
     pub async fn get_devices(&mut self) -> TuyaResult<DevicesResponse> {
-        self.make_request_business(
-            Method::GET,
-            "/v2.0/cloud/thing/device",
-            Some(&[("page_size", "20")]),
-        )
-        .await
+        let page_size = DEVICES_PAGE_SIZE.to_string();
+        let mut devices = DevicesResponse::new();
+
+        loop {
+            // The next page starts after the last device of the previous one
+            let last_id = devices.last().map(|d| d.device_id.clone());
+            let mut params = vec![("page_size", page_size.as_str())];
+            if let Some(last_id) = &last_id {
+                params.push(("last_id", last_id));
+            }
+
+            let page: DevicesResponse = self
+                .make_request_business(Method::GET, "/v2.0/cloud/thing/device", Some(&params), None)
+                .await?;
+
+            // A page that ends with the same device again was already added, Tuya ignored `last_id`
+            if last_id.is_some() && page.last().map(|d| &d.device_id) == last_id.as_ref() {
+                break;
+            }
+
+            let is_last_page = page.len() < DEVICES_PAGE_SIZE;
+            devices.extend(page);
+            if is_last_page {
+                break;
+            }
+        }
+
+        Ok(devices)
     }
 }
@@ -7,20 +7,41 @@ use crate::{
     util::time::get_time,
 };
 use hmac::Mac;
-use reqwest::header::HeaderMap;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
 use serde::de;
+use serde_json::Value;
 use sha2::{Digest, Sha256};
 use url::Url;
 
+// https://developer.tuya.com/en/docs/iot/error-code?id=K989ruxx88swc
+const TOKEN_INVALID: i32 = 1010;
+
 impl TuyaClient {
+    /// Request with the access token, `body` is sent (and signed) as JSON
     pub async fn make_request_business<T: de::DeserializeOwned>(
         &mut self,
         method: reqwest::Method,
         endpoint: &str,
         params: Option<&[(&str, &str)]>,
+        body: Option<&Value>,
     ) -> TuyaResult<T> {
         self.get_access_token().await?;
-        self.make_request(method, endpoint, params, true).await
+        match self
+            .make_request(method.clone(), endpoint, params, body, true)
+            .await
+        {
+            // A (cached) access token can become invalid before it expires, retry once with a new one
+            Err(TuyaError::RequestError {
+                code: TOKEN_INVALID,
+                ..
+            }) => {
+                self.invalidate_access_token();
+                self.get_access_token().await?;
+                self.make_request(method, endpoint, params, body, true)
+                    .await
+            }
+            res => res,
+        }
     }
 
     pub async fn make_request_token<T: de::DeserializeOwned>(
@@ -29,40 +50,44 @@ impl TuyaClient {
         endpoint: &str,
         params: Option<&[(&str, &str)]>,
     ) -> TuyaResult<T> {
-        self.make_request(method, endpoint, params, false).await
+        self.make_request(method, endpoint, params, None, false)
+            .await
     }
 
-    // TODO: Implement body content signing, currently not needed
     async fn make_request<T: de::DeserializeOwned>(
         &mut self,
         method: reqwest::Method,
         endpoint: &str,
         params: Option<&[(&str, &str)]>,
+        body: Option<&Value>,
         business: bool,
     ) -> TuyaResult<T> {
         let t = get_time();
 
-        let url;
-        if let Some(params) = params {
+        let url = if let Some(params) = params {
             // Reqwest seems to internally sort the query params alphabetically.
             // This will invalidate the signed token, so we sort it manually.
             // (.build().url() does not work)
             let mut params = params.to_vec();
             params.sort_by(|a, b| a.0.cmp(b.0));
-            url = Url::parse_with_params(format!("{0}{endpoint}", self.host).as_str(), params)
+            Url::parse_with_params(format!("{0}{endpoint}", self.host).as_str(), params)
         } else {
-            url = Url::parse(format!("{0}{endpoint}", self.host).as_str());
-        }
-        let url: Url = url.map_err(|e| TuyaError::HostUrlParse(e))?;
+            Url::parse(format!("{0}{endpoint}", self.host).as_str())
+        };
+        let url: Url = url.map_err(TuyaError::HostUrlParse)?;
+
+        // The exact body that is sent has to be signed
+        let body = body.map(|b| b.to_string()).unwrap_or_default();
 
-        let res: TuyaResponse<T> = self
+        let mut req = self
             .request_client
             .request(method.clone(), url.clone())
-            .headers(self.create_headers(&t, &method, &url, "", business))
-            .send()
-            .await?
-            .json()
-            .await?;
+            .headers(self.create_headers(&t, &method, &url, &body, business));
+        if !body.is_empty() {
+            req = req.header(CONTENT_TYPE, "application/json").body(body);
+        }
+
+        let res: TuyaResponse<T> = req.send().await?.json().await?;
 
         if res.success {
             Ok(res.result.expect("Should be succesful"))
@@ -88,18 +113,17 @@ impl TuyaClient {
     }
 
     fn sign(&self, t: &u128, string_to_sign: &str, nonce: &u32, business: bool) -> String {
-        let str: String;
-        if business {
-            str = format!(
+        let str: String = if business {
+            format!(
                 "{0}{1}{t}{nonce}{string_to_sign}",
                 self.client_id,
                 self.access_token
                     .clone()
                     .expect("Access token should get retrieved first!")
-            );
+            )
         } else {
-            str = format!("{0}{t}{nonce}{string_to_sign}", self.client_id);
-        }
+            format!("{0}{t}{nonce}{string_to_sign}", self.client_id)
+        };
 
         let mut mac = HmacSha256::new_from_slice(self.client_secret.as_bytes())
             .expect("HMAC can take key of any size");
@@ -143,7 +167,7 @@ impl TuyaClient {
         };
 
         let signed = self.sign(
-            &t,
+            t,
             self.string_to_sign(
                 method.as_str(),
                 body_content,
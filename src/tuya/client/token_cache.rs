@@ -0,0 +1,103 @@
+use crate::tuya::model::auth::CachedToken;
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Stores tokens on disk so they can be reused across invocations.
+///
+/// Every host and client ID combination gets its own file, only readable by the current user.
+#[derive(Debug, Clone)]
+pub struct TokenCache {
+    path: PathBuf,
+}
+
+impl TokenCache {
+    /// Cache in the user's cache directory, if there is one
+    pub fn for_client(host: &str, client_id: &str) -> Option<Self> {
+        let dir = dirs::cache_dir()?
+            .join(env!("CARGO_PKG_NAME"))
+            .join("tokens");
+        Some(Self::in_dir(&dir, host, client_id))
+    }
+
+    pub fn in_dir(dir: &Path, host: &str, client_id: &str) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(host);
+        hasher.update("\n");
+        hasher.update(client_id);
+        let key = hasher.finalize();
+
+        Self {
+            path: dir.join(format!("{:x}.json", key)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns `None` when nothing is cached yet or the cache is unreadable
+    pub fn load(&self) -> Option<CachedToken> {
+        let content = fs::read(&self.path).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    pub fn store(&self, token: &CachedToken) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            create_private_dir(dir)?;
+        }
+
+        // Write to a temporary file first, concurrent invocations never see a partial file.
+        // Every invocation gets its own, so they can't truncate each other's.
+        let tmp = self.path.with_extension(format!(
+            "json.{}.{:08x}.tmp",
+            process::id(),
+            rand::random::<u32>()
+        ));
+        let res = write_private_file(&tmp, &serde_json::to_vec(token)?)
+            .and_then(|_| fs::rename(&tmp, &self.path));
+        if res.is_err() {
+            fs::remove_file(&tmp).ok();
+        }
+        res
+    }
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)
+}
+
+/// Creates a new file, so the permissions can't come from a file that was already there
+#[cfg(unix)]
+fn write_private_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    use std::{io::Write, os::unix::fs::OpenOptionsExt};
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(content)
+}
+
+#[cfg(not(unix))]
+fn write_private_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    file.write_all(content)
+}
@@ -1,5 +1,7 @@
 pub mod auth;
 pub mod date;
 pub mod device;
+pub mod energy;
 pub mod error;
+#[allow(clippy::module_inception)]
 pub mod model;
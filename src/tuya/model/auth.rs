@@ -7,3 +7,12 @@ pub struct TokenResponse {
     pub expire_time: u128,
     pub uid: String,
 }
+
+/// Tokens as stored in the on-disk token cache
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CachedToken {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Unix time in milliseconds
+    pub expiration_time: u128,
+}
@@ -1,2 +1,39 @@
 pub mod year_month;
 pub mod year_month_day;
+pub mod year_month_day_hour;
+
+use crate::util::pretty_string::PrettyString;
+use serde::Serialize;
+use std::fmt::Display;
+use year_month::YearMonth;
+use year_month_day::YearMonthDay;
+
+/// A period energy statistics are reported for
+pub trait DatePeriod: Ord + Display + Clone + PrettyString + Serialize {
+    /// The period after this one
+    fn next(&self) -> Option<Self>;
+
+    /// Month the period falls in
+    fn year_month(&self) -> YearMonth;
+
+    /// Day the period falls in, only known for daily and hourly periods
+    fn day(&self) -> Option<YearMonthDay> {
+        None
+    }
+
+    /// Hour of the day, only known for hourly periods
+    fn hour(&self) -> Option<u8> {
+        None
+    }
+}
+
+/// Periods from `start` on, as long as `keep` holds for the period and its index
+pub fn periods_while<P: DatePeriod>(start: &P, keep: impl Fn(&P, usize) -> bool) -> Vec<P> {
+    let mut periods = Vec::new();
+    let mut period = Some(start.clone());
+    while let Some(p) = period.filter(|p| keep(p, periods.len())) {
+        period = p.next();
+        periods.push(p);
+    }
+    periods
+}
@@ -1,19 +1,18 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
-use time::{error, macros::format_description, Date, Month, OffsetDateTime};
+use time::{
+    error, macros::format_description, util::days_in_year_month, Date, Month, OffsetDateTime,
+};
 
+use super::{year_month_day::YearMonthDay, DatePeriod};
 use crate::util::pretty_string::PrettyString;
 
 #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct YearMonth(Date);
 
 impl YearMonth {
-    pub fn new(year: i32, month: Month) -> Result<Self, error::Parse> {
-        let d = Date::parse(
-            (format!("{:04}{:02}", year, month) + "01").as_str(),
-            format_description!("[year][month][day]"),
-        )?;
-        Ok(Self(d))
+    pub fn new(year: i32, month: Month) -> Result<Self, error::ComponentRange> {
+        Date::from_calendar_date(year, month, 1).map(Self)
     }
 
     pub fn as_string(&self) -> String {
@@ -27,6 +26,59 @@ impl YearMonth {
     pub fn month(&self) -> Month {
         self.0.month()
     }
+
+    pub fn next_month(&self) -> Option<Self> {
+        let d = self
+            .0
+            .replace_day(days_in_year_month(self.year(), self.month()))
+            .ok()?;
+        d.next_day().map(Self::from)
+    }
+
+    pub fn previous_month(&self) -> Option<Self> {
+        self.0.previous_day().map(Self::from)
+    }
+
+    pub fn first_day(&self) -> YearMonthDay {
+        self.0.into()
+    }
+
+    pub fn last_day(&self) -> YearMonthDay {
+        self.0
+            .replace_day(days_in_year_month(self.year(), self.month()))
+            .expect("Last day of the month is valid")
+            .into()
+    }
+
+    /// Same month a year earlier
+    pub fn previous_year(&self) -> Option<Self> {
+        Self::new(self.year() - 1, self.month()).ok()
+    }
+
+    /// Number of months from `self` up to and including `end`
+    pub fn months_until(&self, end: &Self) -> i32 {
+        (end.year() - self.year()) * 12 + end.month() as i32 - self.month() as i32 + 1
+    }
+
+    /// Splits `start..=end` into consecutive ranges of at most `max_months` months
+    pub fn split_range(start: &Self, end: &Self, max_months: usize) -> Vec<(Self, Self)> {
+        let mut ranges = Vec::new();
+        let mut from = Some(start.clone());
+
+        while let Some(f) = from.filter(|f| f <= end) {
+            let mut to = f.clone();
+            for _ in 1..max_months {
+                match to.next_month() {
+                    Some(next) if next <= *end => to = next,
+                    _ => break,
+                }
+            }
+            from = to.next_month();
+            ranges.push((f, to));
+        }
+
+        ranges
+    }
 }
 
 impl PrettyString for YearMonth {
@@ -37,20 +89,25 @@ impl PrettyString for YearMonth {
 
 impl Default for YearMonth {
     fn default() -> Self {
-        let t = OffsetDateTime::now_utc().date();
-        Self(t)
+        Self::from(OffsetDateTime::now_utc().date())
     }
 }
 
-impl Into<String> for &YearMonth {
-    fn into(self) -> String {
-        self.as_string()
+impl From<&YearMonth> for String {
+    fn from(val: &YearMonth) -> Self {
+        val.as_string()
     }
 }
 
-impl Into<Date> for &YearMonth {
-    fn into(self) -> Date {
-        self.0
+impl From<&YearMonth> for Date {
+    fn from(val: &YearMonth) -> Self {
+        val.0
+    }
+}
+
+impl From<Date> for YearMonth {
+    fn from(val: Date) -> Self {
+        Self(val.replace_day(1).expect("First day of the month is valid"))
     }
 }
 
@@ -95,8 +152,72 @@ impl<'de> Deserialize<'de> for YearMonth {
     where
         D: serde::Deserializer<'de>,
     {
-        let s: &str = Deserialize::deserialize(deserializer)?;
+        let s: String = Deserialize::deserialize(deserializer)?;
 
         YearMonth::try_from(s).map_err(serde::de::Error::custom)
     }
 }
+
+impl DatePeriod for YearMonth {
+    fn next(&self) -> Option<Self> {
+        self.next_month()
+    }
+
+    fn year_month(&self) -> YearMonth {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(start: &str, end: &str, max_months: usize) -> Vec<(String, String)> {
+        let start = YearMonth::try_from(start).unwrap();
+        let end = YearMonth::try_from(end).unwrap();
+        YearMonth::split_range(&start, &end, max_months)
+            .iter()
+            .map(|(s, e)| (s.as_string(), e.as_string()))
+            .collect()
+    }
+
+    fn ranges(ranges: &[(&str, &str)]) -> Vec<(String, String)> {
+        ranges
+            .iter()
+            .map(|(s, e)| (s.to_string(), e.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn split_range_fills_every_range_of_an_exact_multiple() {
+        assert_eq!(
+            split("202401", "202412", 6),
+            ranges(&[("202401", "202406"), ("202407", "202412")])
+        );
+        assert_eq!(
+            split("202401", "202412", 12),
+            ranges(&[("202401", "202412")])
+        );
+    }
+
+    #[test]
+    fn split_range_of_a_single_month() {
+        assert_eq!(
+            split("202401", "202401", 12),
+            ranges(&[("202401", "202401")])
+        );
+    }
+
+    #[test]
+    fn split_range_is_empty_when_start_is_after_end() {
+        assert!(split("202402", "202401", 12).is_empty());
+    }
+
+    #[test]
+    fn split_range_crosses_years() {
+        assert_eq!(
+            split("202411", "202502", 3),
+            ranges(&[("202411", "202501"), ("202502", "202502")])
+        );
+    }
+}
@@ -1,7 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
-use time::{macros::format_description, util::days_in_year_month, Date, Month, OffsetDateTime};
+use time::{
+    macros::format_description, util::days_in_year_month, Date, Duration, Month, OffsetDateTime,
+    Weekday,
+};
 
+use super::{year_month::YearMonth, DatePeriod};
 use crate::util::pretty_string::PrettyString;
 
 #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone)]
@@ -9,8 +13,9 @@ pub struct YearMonthDay(Date);
 
 impl YearMonthDay {
     pub fn new(year: i32, month: Month, day: u8) -> Result<Self, &'static str> {
-        let str = format!("{:04}{:02}{:02}", year, month, day);
-        Self::try_from(str)
+        Date::from_calendar_date(year, month, day)
+            .map(Self)
+            .map_err(|_| "Invalid date")
     }
 
     pub fn year(&self) -> i32 {
@@ -25,6 +30,10 @@ impl YearMonthDay {
         self.0.day()
     }
 
+    pub fn weekday(&self) -> Weekday {
+        self.0.weekday()
+    }
+
     pub fn as_string(&self) -> String {
         format!(
             "{:04}{:02}{:02}",
@@ -40,6 +49,43 @@ impl YearMonthDay {
         Self(t.date())
     }
 
+    pub fn next_day(&self) -> Option<Self> {
+        self.0.next_day().map(Self)
+    }
+
+    pub fn days_before(&self, days: i64) -> Option<Self> {
+        self.0.checked_sub(Duration::days(days)).map(Self)
+    }
+
+    /// Same day a year earlier, 29 February becomes 28 February
+    pub fn previous_year(&self) -> Option<Self> {
+        let year = self.year() - 1;
+        Self::new(year, self.month(), self.day())
+            .or_else(|_| Self::new(year, self.month(), self.day() - 1))
+            .ok()
+    }
+
+    /// Number of days from `self` up to and including `end`
+    pub fn days_until(&self, end: &Self) -> i64 {
+        (end.0 - self.0).whole_days() + 1
+    }
+
+    /// Splits `start..=end` into consecutive ranges of at most `max_days` days
+    pub fn split_range(start: &Self, end: &Self, max_days: i64) -> Vec<(Self, Self)> {
+        let mut ranges = Vec::new();
+        let mut from = Some(start.0);
+
+        while let Some(f) = from.filter(|f| *f <= end.0) {
+            let to = f
+                .checked_add(Duration::days(max_days - 1))
+                .map_or(end.0, |t| t.min(end.0));
+            ranges.push((Self(f), Self(to)));
+            from = to.next_day();
+        }
+
+        ranges
+    }
+
     pub fn last_day_current_month() -> Self {
         let t = OffsetDateTime::now_utc();
         let t = t
@@ -62,19 +108,25 @@ impl Default for YearMonthDay {
     }
 }
 
-impl Into<String> for &YearMonthDay {
-    fn into(self) -> String {
-        self.as_string()
+impl From<&YearMonthDay> for String {
+    fn from(val: &YearMonthDay) -> Self {
+        val.as_string()
     }
 }
 
-impl Into<Date> for &YearMonthDay {
-    fn into(self) -> Date {
-        Date::parse(&self.as_string(), format_description!("[year][month][day]"))
+impl From<&YearMonthDay> for Date {
+    fn from(val: &YearMonthDay) -> Self {
+        Date::parse(&val.as_string(), format_description!("[year][month][day]"))
             .expect("Self contained format")
     }
 }
 
+impl From<Date> for YearMonthDay {
+    fn from(val: Date) -> Self {
+        Self(val)
+    }
+}
+
 impl Display for YearMonthDay {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(f, "{}", self.as_string())
@@ -84,10 +136,8 @@ impl Display for YearMonthDay {
 impl TryFrom<&str> for YearMonthDay {
     type Error = &'static str;
     fn try_from(s: &str) -> Result<Self, Self::Error> {
-        let str = format!("{}", s);
         let f = format_description!("[year][month][day]");
-        let t =
-            Date::parse(&str, f).map_err(|_| "Failed to parse date, expects format 'yyyymmdd'")?;
+        let t = Date::parse(s, f).map_err(|_| "Failed to parse date, expects format 'yyyymmdd'")?;
 
         Ok(Self(t))
     }
@@ -116,8 +166,86 @@ impl<'de> Deserialize<'de> for YearMonthDay {
     where
         D: serde::Deserializer<'de>,
     {
-        let s: &str = Deserialize::deserialize(deserializer)?;
+        // Owned, borrowing fails for escaped JSON strings and query parameters
+        let s: String = Deserialize::deserialize(deserializer)?;
 
         YearMonthDay::try_from(s).map_err(serde::de::Error::custom)
     }
 }
+
+impl DatePeriod for YearMonthDay {
+    fn next(&self) -> Option<Self> {
+        self.next_day()
+    }
+
+    fn year_month(&self) -> YearMonth {
+        self.0.into()
+    }
+
+    fn day(&self) -> Option<YearMonthDay> {
+        Some(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(start: &str, end: &str, max_days: i64) -> Vec<(String, String)> {
+        let start = YearMonthDay::try_from(start).unwrap();
+        let end = YearMonthDay::try_from(end).unwrap();
+        YearMonthDay::split_range(&start, &end, max_days)
+            .iter()
+            .map(|(s, e)| (s.as_string(), e.as_string()))
+            .collect()
+    }
+
+    fn ranges(ranges: &[(&str, &str)]) -> Vec<(String, String)> {
+        ranges
+            .iter()
+            .map(|(s, e)| (s.to_string(), e.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn split_range_fills_every_range_of_an_exact_multiple() {
+        assert_eq!(
+            split("20250101", "20250110", 5),
+            ranges(&[("20250101", "20250105"), ("20250106", "20250110")])
+        );
+        assert_eq!(
+            split("20250101", "20250131", 31),
+            ranges(&[("20250101", "20250131")])
+        );
+    }
+
+    #[test]
+    fn split_range_of_a_single_day() {
+        assert_eq!(
+            split("20250101", "20250101", 31),
+            ranges(&[("20250101", "20250101")])
+        );
+    }
+
+    #[test]
+    fn split_range_is_empty_when_start_is_after_end() {
+        assert!(split("20250102", "20250101", 31).is_empty());
+    }
+
+    #[test]
+    fn split_range_crosses_years() {
+        assert_eq!(
+            split("20241220", "20250110", 10),
+            ranges(&[
+                ("20241220", "20241229"),
+                ("20241230", "20250108"),
+                ("20250109", "20250110"),
+            ])
+        );
+
+        // The 366 days of a leap year, the last range gets what is left
+        let year = split("20240101", "20241231", 31);
+        assert_eq!(year.len(), 12);
+        assert_eq!(year[11], ("20241207".to_string(), "20241231".to_string()));
+    }
+}
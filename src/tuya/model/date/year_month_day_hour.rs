@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use time::{macros::format_description, Duration, OffsetDateTime, PrimitiveDateTime, Time};
+
+use super::{year_month::YearMonth, year_month_day::YearMonthDay, DatePeriod};
+use crate::util::pretty_string::PrettyString;
+
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct YearMonthDayHour(PrimitiveDateTime);
+
+impl YearMonthDayHour {
+    pub fn new(day: &YearMonthDay, hour: u8) -> Result<Self, &'static str> {
+        let time = Time::from_hms(hour, 0, 0).map_err(|_| "Invalid hour")?;
+        Ok(Self(PrimitiveDateTime::new(day.into(), time)))
+    }
+
+    pub fn day(&self) -> YearMonthDay {
+        self.0.date().into()
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.0.hour()
+    }
+
+    pub fn as_string(&self) -> String {
+        format!("{}{:02}", self.day().as_string(), self.hour())
+    }
+
+    pub fn first_hour_today() -> Self {
+        Self::new(&YearMonthDay::default(), 0).expect("Midnight is valid")
+    }
+
+    pub fn next_hour(&self) -> Option<Self> {
+        self.0.checked_add(Duration::HOUR).map(Self)
+    }
+
+    /// Splits `start..=end` into consecutive ranges of at most `max_hours` hours
+    pub fn split_range(start: &Self, end: &Self, max_hours: i64) -> Vec<(Self, Self)> {
+        let mut ranges = Vec::new();
+        let mut from = Some(start.0);
+
+        while let Some(f) = from.filter(|f| *f <= end.0) {
+            let to = f
+                .checked_add(Duration::hours(max_hours - 1))
+                .map_or(end.0, |t| t.min(end.0));
+            ranges.push((Self(f), Self(to)));
+            from = to.checked_add(Duration::HOUR);
+        }
+
+        ranges
+    }
+}
+
+impl PrettyString for YearMonthDayHour {
+    fn as_pretty_string(&self) -> String {
+        format!("{} {:02}:00", self.day().as_pretty_string(), self.hour())
+    }
+}
+
+impl Default for YearMonthDayHour {
+    /// The current hour
+    fn default() -> Self {
+        let t = OffsetDateTime::now_utc();
+        Self::new(&t.date().into(), t.hour()).expect("Current hour is valid")
+    }
+}
+
+impl From<&YearMonthDayHour> for String {
+    fn from(val: &YearMonthDayHour) -> Self {
+        val.as_string()
+    }
+}
+
+impl Display for YearMonthDayHour {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_string())
+    }
+}
+
+impl TryFrom<&str> for YearMonthDayHour {
+    type Error = &'static str;
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let f = format_description!("[year][month][day][hour]");
+        let t = PrimitiveDateTime::parse(s, f)
+            .map_err(|_| "Failed to parse date, expects format 'yyyymmddhh'")?;
+
+        Ok(Self(t))
+    }
+}
+
+impl TryFrom<String> for YearMonthDayHour {
+    type Error = &'static str;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::try_from(s.as_str())
+    }
+}
+
+// Serde
+impl Serialize for YearMonthDayHour {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let str: String = self.into();
+        serializer.serialize_str(str.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for YearMonthDayHour {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+
+        YearMonthDayHour::try_from(s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl DatePeriod for YearMonthDayHour {
+    fn next(&self) -> Option<Self> {
+        self.next_hour()
+    }
+
+    fn year_month(&self) -> YearMonth {
+        self.0.date().into()
+    }
+
+    fn day(&self) -> Option<YearMonthDay> {
+        Some(self.day())
+    }
+
+    fn hour(&self) -> Option<u8> {
+        Some(self.hour())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(start: &str, end: &str, max_hours: i64) -> Vec<(String, String)> {
+        let start = YearMonthDayHour::try_from(start).unwrap();
+        let end = YearMonthDayHour::try_from(end).unwrap();
+        YearMonthDayHour::split_range(&start, &end, max_hours)
+            .iter()
+            .map(|(s, e)| (s.as_string(), e.as_string()))
+            .collect()
+    }
+
+    fn ranges(ranges: &[(&str, &str)]) -> Vec<(String, String)> {
+        ranges
+            .iter()
+            .map(|(s, e)| (s.to_string(), e.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn split_range_fills_every_range_of_an_exact_multiple() {
+        assert_eq!(
+            split("2025010100", "2025010123", 12),
+            ranges(&[("2025010100", "2025010111"), ("2025010112", "2025010123")])
+        );
+        assert_eq!(
+            split("2025010100", "2025010123", 24),
+            ranges(&[("2025010100", "2025010123")])
+        );
+    }
+
+    #[test]
+    fn split_range_of_a_single_hour() {
+        assert_eq!(
+            split("2025010100", "2025010100", 24),
+            ranges(&[("2025010100", "2025010100")])
+        );
+    }
+
+    #[test]
+    fn split_range_is_empty_when_start_is_after_end() {
+        assert!(split("2025010101", "2025010100", 24).is_empty());
+    }
+
+    #[test]
+    fn split_range_crosses_years() {
+        assert_eq!(
+            split("2024123122", "2025010101", 3),
+            ranges(&[("2024123122", "2025010100"), ("2025010101", "2025010101")])
+        );
+    }
+}
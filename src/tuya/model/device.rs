@@ -1,13 +1,23 @@
 use crate::util::pretty_string::PrettyString;
 
-use super::date::{year_month::YearMonth, year_month_day::YearMonthDay};
-use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use super::date::{
+    year_month::YearMonth, year_month_day::YearMonthDay, year_month_day_hour::YearMonthDayHour,
+    DatePeriod,
+};
+use super::energy::Energy;
+use rust_decimal::Decimal;
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+};
+use time::{macros::format_description, OffsetDateTime};
 
 pub type DevicesResponse = Vec<DeviceResponse>;
 
 // https://developer.tuya.com/en/docs/cloud/734e8088a6?id=Kcspwthd1f5tb
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DeviceResponse {
     #[serde(rename = "id")]
     pub device_id: String,
@@ -21,14 +31,14 @@ pub struct DeviceResponse {
 
 impl DeviceResponse {
     pub fn get_name(&self) -> &str {
-        return if self.custom_name.len() == 0 {
+        if self.custom_name.is_empty() {
             &self.product_name
         } else {
             &self.custom_name
-        };
+        }
     }
 
-    pub fn print(&self, index: Option<usize>, to_print: impl PrettyString) {
+    pub fn print(&self, index: Option<usize>, to_print: &impl PrettyString) {
         println!(
             "[{}] {}:\n{}",
             index.unwrap_or(0) + 1,
@@ -38,65 +48,502 @@ impl DeviceResponse {
     }
 }
 
-// https://developer.tuya.com/en/docs/cloud/734e8088a6?id=Kcspwthd1f5tb
+impl From<DeviceInfo> for DeviceResponse {
+    fn from(value: DeviceInfo) -> Self {
+        Self {
+            device_id: value.device_id,
+            product_name: value.product_name,
+            custom_name: value.custom_name,
+            is_online: value.is_online,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    #[serde(rename = "id")]
+    pub device_id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub custom_name: String,
+    #[serde(default)]
+    pub product_name: String,
+    #[serde(default)]
+    pub product_id: String,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub ip: String,
+    #[serde(default)]
+    pub time_zone: String,
+    /// Missing for devices that were never activated
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub active_time: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub create_time: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub update_time: Option<OffsetDateTime>,
+    pub is_online: bool,
+    /// Never shown, only serialized as `hasLocalKey`
+    #[serde(
+        default,
+        rename(serialize = "hasLocalKey"),
+        serialize_with = "serialize_presence"
+    )]
+    local_key: String,
+}
+
+fn serialize_presence<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bool(!value.is_empty())
+}
+
+impl DeviceInfo {
+    pub fn get_name(&self) -> &str {
+        if self.custom_name.is_empty() {
+            &self.name
+        } else {
+            &self.custom_name
+        }
+    }
+
+    pub fn has_local_key(&self) -> bool {
+        !self.local_key.is_empty()
+    }
+}
+
+impl PrettyString for DeviceInfo {
+    fn as_pretty_string(&self) -> String {
+        let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second] UTC");
+        let time =
+            |t: Option<OffsetDateTime>| t.and_then(|t| t.format(format).ok()).unwrap_or_default();
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+
+        let fields = [
+            ("Name", self.get_name().to_string()),
+            ("ID", self.device_id.clone()),
+            (
+                "Product",
+                format!("{} ({})", self.product_name, self.product_id),
+            ),
+            ("Category", self.category.clone()),
+            ("Online", yes_no(self.is_online).into()),
+            ("IP", self.ip.clone()),
+            ("Time zone", self.time_zone.clone()),
+            ("Activated", time(self.active_time)),
+            ("Created", time(self.create_time)),
+            ("Updated", time(self.update_time)),
+            ("Local key", yes_no(self.has_local_key()).into()),
+        ];
+        let padding = fields.iter().map(|f| f.0.len()).max().unwrap_or(0);
+
+        fields
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "* {: <width$} {}",
+                    name.to_string() + ":",
+                    value,
+                    width = padding + 1
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
-pub struct DeviceMonthlyStatistics {
-    pub months: BTreeMap<YearMonth, String>,
+pub struct DeviceCommand {
+    pub code: String,
+    pub value: Value,
 }
 
-impl PrettyString for DeviceMonthlyStatistics {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeviceProperty {
+    pub code: String,
+    #[serde(default)]
+    pub custom_name: String,
+    pub dp_id: u32,
+    /// Time of the last report, unix time in milliseconds
+    pub time: u64,
+    #[serde(rename = "type")]
+    pub value_type: String,
+    pub value: Value,
+}
+
+/// Device shadow properties as Tuya returns them
+#[derive(Deserialize)]
+pub struct DeviceShadow {
+    pub properties: Vec<DeviceProperty>,
+}
+
+/// Device shadow properties, with the energy metering codes decoded into physical units.
+///
+/// All properties are kept in `properties`, including the ones that are decoded.
+#[derive(Serialize, Debug, Clone)]
+pub struct DeviceProperties {
+    /// Current power in W (`cur_power`)
+    pub power: Option<f64>,
+    /// Current voltage in V (`cur_voltage`)
+    pub voltage: Option<f64>,
+    /// Current in A (`cur_current`)
+    pub current: Option<f64>,
+    /// Energy added since the previous report in kWh (`add_ele`), not a running total
+    pub energy: Option<f64>,
+    pub properties: Vec<DeviceProperty>,
+}
+
+impl DeviceProperties {
+    /// Decodes the metering codes with the scales of the device's thing model
+    pub fn new(properties: Vec<DeviceProperty>, model: &ThingModel) -> Self {
+        let p = &properties;
+        Self {
+            power: Self::scaled(p, "cur_power", model),
+            voltage: Self::scaled(p, "cur_voltage", model),
+            current: Self::scaled(p, "cur_current", model),
+            energy: Self::scaled(p, "add_ele", model),
+            properties,
+        }
+    }
+
+    pub fn get(&self, code: &str) -> Option<&DeviceProperty> {
+        self.properties.iter().find(|p| p.code == code)
+    }
+
+    /// Numeric value of `code`, in the unit of its reading
+    fn scaled(properties: &[DeviceProperty], code: &str, model: &ThingModel) -> Option<f64> {
+        let value = properties.iter().find(|p| p.code == code)?.value.as_f64()?;
+        Some(value / model.spec(code)?.divisor())
+    }
+}
+
+/// Specs of the metering codes most meters report, used when the thing model doesn't describe them
+static DEFAULT_SPECS: [(&str, ValueSpec); 4] = [
+    ("cur_power", ValueSpec::new(1, "W")),
+    ("cur_voltage", ValueSpec::new(1, "V")),
+    ("cur_current", ValueSpec::new(0, "mA")),
+    ("add_ele", ValueSpec::new(3, "kW·h")),
+];
+
+/// How a numeric property code is reported: an integer with `scale` decimals, in `unit`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ValueSpec {
+    #[serde(default)]
+    pub scale: u32,
+    #[serde(default)]
+    pub unit: Cow<'static, str>,
+}
+
+impl ValueSpec {
+    const fn new(scale: u32, unit: &'static str) -> Self {
+        Self {
+            scale,
+            unit: Cow::Borrowed(unit),
+        }
+    }
+
+    /// Divisor from the reported integer to the reading in W, V, A or kWh
+    fn divisor(&self) -> f64 {
+        let unit = match self.unit.as_ref() {
+            "mA" | "mV" | "Wh" | "W·h" => 1000.0,
+            _ => 1.0,
+        };
+        unit * 10f64.powi(self.scale as i32)
+    }
+}
+
+/// The numeric property codes of a device's product, their scale is set per product.
+// https://developer.tuya.com/en/docs/cloud/thing-model?id=Kcspwthd1f5tb
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(try_from = "RawThingModel")]
+pub struct ThingModel {
+    specs: HashMap<String, ValueSpec>,
+}
+
+impl ThingModel {
+    /// Spec of `code`, the common one for metering codes the model doesn't describe
+    pub fn spec(&self, code: &str) -> Option<&ValueSpec> {
+        self.specs.get(code).or_else(|| {
+            DEFAULT_SPECS
+                .iter()
+                .find(|(c, _)| *c == code)
+                .map(|(_, spec)| spec)
+        })
+    }
+}
+
+/// The model is a JSON document in a string
+#[derive(Deserialize)]
+struct RawThingModel {
+    model: String,
+}
+
+#[derive(Deserialize)]
+struct ThingModelServices {
+    #[serde(default)]
+    services: Vec<ThingModelService>,
+}
+
+#[derive(Deserialize)]
+struct ThingModelService {
+    #[serde(default)]
+    properties: Vec<ThingModelProperty>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThingModelProperty {
+    code: String,
+    type_spec: Value,
+}
+
+impl TryFrom<RawThingModel> for ThingModel {
+    type Error = serde_json::Error;
+
+    fn try_from(value: RawThingModel) -> Result<Self, Self::Error> {
+        let model: ThingModelServices = serde_json::from_str(&value.model)?;
+        let specs = model
+            .services
+            .into_iter()
+            .flat_map(|s| s.properties)
+            .filter(|p| p.type_spec.get("type").and_then(Value::as_str) == Some("value"))
+            .map(|p| Ok((p.code, serde_json::from_value(p.type_spec)?)))
+            .collect::<Result<_, Self::Error>>()?;
+        Ok(Self { specs })
+    }
+}
+
+impl PrettyString for DeviceProperties {
     fn as_pretty_string(&self) -> String {
+        let readings = [
+            ("Power", self.power, "W"),
+            ("Voltage", self.voltage, "V"),
+            ("Current", self.current, "A"),
+            ("Energy", self.energy, "kWh"),
+        ];
         let padding = self
-            .months
+            .properties
             .iter()
-            .map(|m| m.0.as_pretty_string().len())
-            .max();
+            .map(|p| p.code.len())
+            .chain(readings.iter().map(|r| r.0.len()))
+            .max()
+            .unwrap_or(0);
 
-        return if let Some(padding) = padding {
-            let s = self
-                .months
-                .iter()
-                .map(|m| {
+        readings
+            .iter()
+            .filter_map(|(name, value, unit)| {
+                value.map(|v| {
                     format!(
-                        "* {: <width$} {} kWh",
-                        m.0.as_pretty_string() + ":",
-                        m.1,
-                        width = padding + 1,
+                        "* {: <width$} {} {}",
+                        name.to_string() + ":",
+                        v,
+                        unit,
+                        width = padding + 1
                     )
                 })
-                .collect::<Vec<String>>();
-            s.join("\n")
-        } else {
-            "".into()
+            })
+            .chain(self.properties.iter().map(|p| {
+                format!(
+                    "* {: <width$} {}",
+                    p.code.clone() + ":",
+                    p.value,
+                    width = padding + 1
+                )
+            }))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Energy consumption per period of a device
+pub trait Statistics: DeviceResult {
+    type Period: DatePeriod;
+
+    fn periods(&self) -> &BTreeMap<Self::Period, Energy>;
+
+    /// Total, mean and extremes of the consumption
+    fn summary(&self) -> Option<Summary<'_, Self::Period>> {
+        let mut values = self.periods().iter();
+        let first = values.next()?;
+        let mut summary = Summary {
+            total: *first.1,
+            mean: *first.1,
+            min: first,
+            max: first,
         };
+
+        for value in values {
+            summary.total += *value.1;
+            if value.1 < summary.min.1 {
+                summary.min = value;
+            }
+            if value.1 > summary.max.1 {
+                summary.max = value;
+            }
+        }
+        summary.mean = Energy::from_kwh(summary.total.kwh() / Decimal::from(self.periods().len()));
+
+        Some(summary)
+    }
+
+    fn total(&self) -> Energy {
+        self.periods().values().sum()
+    }
+}
+
+/// Total, mean and extremes of statistics
+#[derive(Debug)]
+pub struct Summary<'a, P> {
+    pub total: Energy,
+    pub mean: Energy,
+    pub min: (&'a P, &'a Energy),
+    pub max: (&'a P, &'a Energy),
+}
+
+impl<P: DatePeriod> PrettyString for Summary<'_, P> {
+    fn as_pretty_string(&self) -> String {
+        [
+            format!("Total:   {}", self.total),
+            format!("Average: {}", Energy::from_kwh(self.mean.kwh().round_dp(3))),
+            format!(
+                "Minimum: {} ({})",
+                self.min.1,
+                self.min.0.as_pretty_string()
+            ),
+            format!(
+                "Maximum: {} ({})",
+                self.max.1,
+                self.max.0.as_pretty_string()
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+/// Lists every period, followed by a summary
+fn pretty_statistics<S: Statistics>(stats: &S) -> String {
+    let padding = stats
+        .periods()
+        .keys()
+        .map(|p| p.as_pretty_string().len())
+        .max();
+
+    let Some(padding) = padding else {
+        return "".into();
+    };
+
+    let mut s = stats
+        .periods()
+        .iter()
+        .map(|(period, kwh)| {
+            format!(
+                "* {: <width$} {}",
+                period.as_pretty_string() + ":",
+                kwh,
+                width = padding + 1,
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    if let Some(summary) = stats.summary() {
+        s.push_str("\n\n");
+        s.push_str(&summary.as_pretty_string());
+    }
+    s
+}
+
+/// A result of a single device, paired with the device it belongs to.
+///
+/// Serialized as `{"device": ..., "<T::KEY>": ...}`, e.g. `"statistics"` for statistics.
+#[derive(Debug)]
+pub struct ForDevice<T> {
+    pub device: DeviceResponse,
+    pub value: T,
+}
+
+/// Results that are listed per device
+pub trait DeviceResult {
+    /// Field the result is serialized in, next to the device
+    const KEY: &'static str;
+}
+
+impl<T: DeviceResult + Serialize> Serialize for ForDevice<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("ForDevice", 2)?;
+        s.serialize_field("device", &self.device)?;
+        s.serialize_field(T::KEY, &self.value)?;
+        s.end()
     }
 }
 
+impl DeviceResult for DeviceMonthlyStatistics {
+    const KEY: &'static str = "statistics";
+}
+
+impl DeviceResult for DeviceDailyStatistics {
+    const KEY: &'static str = "statistics";
+}
+
+impl DeviceResult for DeviceHourlyStatistics {
+    const KEY: &'static str = "statistics";
+}
+
+// https://developer.tuya.com/en/docs/cloud/734e8088a6?id=Kcspwthd1f5tb
 #[derive(Serialize, Deserialize, Debug)]
+pub struct DeviceMonthlyStatistics {
+    pub months: BTreeMap<YearMonth, Energy>,
+}
+
+impl Statistics for DeviceMonthlyStatistics {
+    type Period = YearMonth;
+
+    fn periods(&self) -> &BTreeMap<Self::Period, Energy> {
+        &self.months
+    }
+}
+
+impl PrettyString for DeviceMonthlyStatistics {
+    fn as_pretty_string(&self) -> String {
+        pretty_statistics(self)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct DeviceDailyStatistics {
-    pub days: BTreeMap<YearMonthDay, String>,
+    pub days: BTreeMap<YearMonthDay, Energy>,
+}
+
+impl Statistics for DeviceDailyStatistics {
+    type Period = YearMonthDay;
+
+    fn periods(&self) -> &BTreeMap<Self::Period, Energy> {
+        &self.days
+    }
 }
 
 impl PrettyString for DeviceDailyStatistics {
     fn as_pretty_string(&self) -> String {
-        let padding = self.days.iter().map(|d| d.0.as_pretty_string().len()).max();
+        pretty_statistics(self)
+    }
+}
 
-        return if let Some(padding) = padding {
-            let s = self
-                .days
-                .iter()
-                .map(|d| {
-                    format!(
-                        "* {: <width$} {} kWh",
-                        d.0.as_pretty_string() + ":",
-                        d.1,
-                        width = padding + 1,
-                    )
-                })
-                .collect::<Vec<String>>();
-            s.join("\n")
-        } else {
-            "".into()
-        };
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeviceHourlyStatistics {
+    pub hours: BTreeMap<YearMonthDayHour, Energy>,
+}
+
+impl Statistics for DeviceHourlyStatistics {
+    type Period = YearMonthDayHour;
+
+    fn periods(&self) -> &BTreeMap<Self::Period, Energy> {
+        &self.hours
+    }
+}
+
+impl PrettyString for DeviceHourlyStatistics {
+    fn as_pretty_string(&self) -> String {
+        pretty_statistics(self)
     }
 }
@@ -0,0 +1,194 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    iter::Sum,
+    ops::{Add, AddAssign, Sub},
+    str::FromStr,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnergyUnit {
+    Wh,
+    KWh,
+    MWh,
+}
+
+impl EnergyUnit {
+    /// Number of Wh in one unit
+    fn wh(&self) -> Decimal {
+        match self {
+            Self::Wh => Decimal::ONE,
+            Self::KWh => Decimal::ONE_THOUSAND,
+            Self::MWh => Decimal::from(1_000_000),
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Wh => "Wh",
+            Self::KWh => "kWh",
+            Self::MWh => "MWh",
+        }
+    }
+}
+
+/// An exact amount of energy.
+///
+/// Tuya reports kWh as strings (e.g. `"1.36"`), which is also how it serializes.
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub struct Energy(Decimal);
+
+impl Energy {
+    pub const ZERO: Self = Self(Decimal::ZERO);
+
+    pub fn new(value: Decimal, unit: EnergyUnit) -> Self {
+        Self(value * unit.wh() / EnergyUnit::KWh.wh())
+    }
+
+    pub fn from_kwh(kwh: Decimal) -> Self {
+        Self(kwh)
+    }
+
+    pub fn kwh(&self) -> Decimal {
+        self.0
+    }
+}
+
+// Nothing in the CLI works in other units yet
+#[allow(dead_code)]
+impl Energy {
+    /// Value in `unit`
+    pub fn to(&self, unit: EnergyUnit) -> Decimal {
+        (self.0 * EnergyUnit::KWh.wh() / unit.wh()).normalize()
+    }
+
+    pub fn wh(&self) -> Decimal {
+        self.to(EnergyUnit::Wh)
+    }
+
+    pub fn mwh(&self) -> Decimal {
+        self.to(EnergyUnit::MWh)
+    }
+}
+
+impl Add for Energy {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Energy {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Energy {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Sum for Energy {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Energy> for Energy {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+impl Display for Energy {
+    /// In kWh with the unit, formatting options (e.g. precision) apply to the value
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        Display::fmt(&self.0, f)?;
+        write!(f, " kWh")
+    }
+}
+
+impl FromStr for Energy {
+    type Err = &'static str;
+
+    /// A number in kWh as Tuya sends it, or a number followed by `Wh`, `kWh` or `MWh`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (value, unit) = [EnergyUnit::MWh, EnergyUnit::KWh, EnergyUnit::Wh]
+            .into_iter()
+            .find_map(|u| Some((s.strip_suffix(u.symbol())?, u)))
+            .unwrap_or((s, EnergyUnit::KWh));
+
+        let value = Decimal::from_str(value.trim()).map_err(|_| "Invalid energy value")?;
+        Ok(Self::new(value, unit))
+    }
+}
+
+// Serde
+impl Serialize for Energy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Energy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // Decimal accepts both strings and numbers
+        let value = <Decimal as Deserialize>::deserialize(deserializer)?;
+        Ok(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kwh(kwh: &str) -> Energy {
+        Energy::from_kwh(Decimal::from_str(kwh).unwrap())
+    }
+
+    #[test]
+    fn parses_tuya_values_and_units() {
+        assert_eq!("1.36".parse(), Ok(kwh("1.36")));
+        assert_eq!("1.5 kWh".parse(), Ok(kwh("1.5")));
+        assert_eq!("1500Wh".parse(), Ok(kwh("1.5")));
+        assert_eq!(" 0.002 MWh ".parse(), Ok(kwh("2")));
+        assert_eq!("1.5 GWh".parse::<Energy>(), Err("Invalid energy value"));
+        assert_eq!("".parse::<Energy>(), Err("Invalid energy value"));
+    }
+
+    #[test]
+    fn converts_between_units() {
+        let energy = kwh("1.5");
+
+        assert_eq!(energy.wh(), Decimal::from(1500));
+        assert_eq!(energy.mwh(), Decimal::from_str("0.0015").unwrap());
+        assert_eq!(
+            energy.to(EnergyUnit::KWh),
+            Decimal::from_str("1.5").unwrap()
+        );
+        assert_eq!(Energy::new(Decimal::from(250), EnergyUnit::Wh), kwh("0.25"));
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let json = serde_json::to_string(&kwh("1.36")).unwrap();
+        assert_eq!(json, "\"1.36\"");
+        assert_eq!(serde_json::from_str::<Energy>(&json).unwrap(), kwh("1.36"));
+
+        // Numbers are accepted as well
+        assert_eq!(serde_json::from_str::<Energy>("1.36").unwrap(), kwh("1.36"));
+    }
+}
@@ -23,7 +23,7 @@ impl From<reqwest::Error> for TuyaError {
 impl Display for TuyaError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str: String = match self {
-            Self::HostUrlParse(e) => format!("Host URL failed to parse: {}", e.to_string()),
+            Self::HostUrlParse(e) => format!("Host URL failed to parse: {}", e),
             Self::RequestError { msg, code, t, tid } => {
                 format!(
                     "Tuya request was unsuccesful:\n\
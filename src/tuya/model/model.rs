@@ -5,6 +5,8 @@ use sha2::Sha256;
 
 use super::error::TuyaError;
 
+// Not read yet, see `TuyaClient::string_to_sign`
+#[allow(dead_code)]
 pub struct Headers(pub HeaderMap);
 pub type HmacSha256 = Hmac<Sha256>;
 pub type TuyaResult<T> = Result<T, TuyaError>;
@@ -1,2 +1,5 @@
+pub mod csv_export;
+pub mod json;
+pub mod poll;
 pub mod pretty_string;
 pub mod time;
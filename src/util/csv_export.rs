@@ -0,0 +1,85 @@
+use crate::tuya::model::device::{ForDevice, Statistics};
+use std::{collections::BTreeSet, io};
+
+/// Writes one `device_id,device_name,period,kwh` row per device and period
+pub fn write_long<T: Statistics>(stats: &[ForDevice<T>], out: impl io::Write) -> csv::Result<()> {
+    let mut w = csv::Writer::from_writer(out);
+    w.write_record(["device_id", "device_name", "period", "kwh"])?;
+
+    for s in stats {
+        for (period, kwh) in s.value.periods() {
+            w.write_record([
+                s.device.device_id.as_str(),
+                s.device.get_name(),
+                period.to_string().as_str(),
+                kwh.kwh().to_string().as_str(),
+            ])?;
+        }
+    }
+
+    w.flush()?;
+    Ok(())
+}
+
+/// Writes one row per period with a kWh column for every device, named `device_name (device_id)`
+/// because devices of the same product share their name.
+/// Cells are left empty when a device has no value for that period.
+pub fn write_wide<T: Statistics>(stats: &[ForDevice<T>], out: impl io::Write) -> csv::Result<()> {
+    let mut w = csv::Writer::from_writer(out);
+
+    let mut header = vec!["period".to_string()];
+    header.extend(
+        stats
+            .iter()
+            .map(|s| format!("{} ({})", s.device.get_name(), s.device.device_id)),
+    );
+    w.write_record(&header)?;
+
+    let periods: BTreeSet<&T::Period> = stats
+        .iter()
+        .flat_map(|s| s.value.periods().keys())
+        .collect();
+
+    for period in periods {
+        let mut row = vec![period.to_string()];
+        row.extend(stats.iter().map(|s| {
+            s.value
+                .periods()
+                .get(period)
+                .map(|e| e.kwh().to_string())
+                .unwrap_or_default()
+        }));
+        w.write_record(&row)?;
+    }
+
+    w.flush()?;
+    Ok(())
+}
+
+/// Results of a device that are exported as CSV, a row per period, day etc.
+pub trait CsvRows {
+    /// Columns after `device_id,device_name`
+    const COLUMNS: &'static [&'static str];
+
+    /// Cells of every row, in the order of the columns, missing values are empty
+    fn csv_rows(&self) -> Vec<Vec<String>>;
+}
+
+/// Writes one `device_id,device_name,<T::COLUMNS>` row per row of the result of every device
+pub fn write_rows<T: CsvRows>(results: &[ForDevice<T>], out: impl io::Write) -> csv::Result<()> {
+    let mut w = csv::Writer::from_writer(out);
+    w.write_record(["device_id", "device_name"].iter().chain(T::COLUMNS))?;
+
+    for r in results {
+        for row in r.value.csv_rows() {
+            w.write_record(
+                [r.device.device_id.as_str(), r.device.get_name()]
+                    .into_iter()
+                    .chain(row.iter().map(String::as_str)),
+            )?;
+        }
+    }
+
+    w.flush()?;
+    Ok(())
+}
@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+/// JSON of a model on a single line
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).expect("Models serialize to JSON")
+}
+
+pub fn print_json<T: Serialize + ?Sized>(value: &T) {
+    println!("{}", to_json(value));
+}
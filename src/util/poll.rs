@@ -0,0 +1,25 @@
+use crate::error::AppResult;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+/// Runs `poll` right away and then every `interval`, until Ctrl+C is pressed or it fails.
+///
+/// A slow poll isn't followed by a burst of polls to catch up,
+/// and Ctrl+C also stops it in the middle of a poll.
+pub async fn every(
+    interval: Duration,
+    mut poll: impl AsyncFnMut() -> AppResult<()>,
+) -> AppResult<()> {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            result = async {
+                ticker.tick().await;
+                poll().await
+            } => result?,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    args::OutputFormat,
+    error::{AppError, AppResult},
+    tuya::{
+        client::TuyaClient,
+        model::device::{DeviceProperties, DeviceResponse, DevicesResponse},
+    },
+    util::{json::print_json, poll},
+};
+use serde::Serialize;
+use std::{
+    io::{self, IsTerminal, Write},
+    time::Duration,
+};
+use time::{format_description::well_known::Rfc3339, macros::format_description, OffsetDateTime};
+
+/// Moves the cursor to the top left and clears the terminal
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+
+/// Latest power, voltage and current of a device, or why they couldn't be read
+#[derive(Serialize, Debug)]
+pub struct Reading {
+    pub device: DeviceResponse,
+    /// Current power in W
+    pub power: Option<f64>,
+    /// Current voltage in V
+    pub voltage: Option<f64>,
+    /// Current in A
+    pub current: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Readings of all watched devices at a moment in time
+#[derive(Serialize, Debug)]
+pub struct Poll {
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+    pub readings: Vec<Reading>,
+}
+
+/// Polls the properties of the devices every `interval` until Ctrl+C is pressed.
+///
+/// Pretty output redraws a table in the terminal, JSON prints one document per line
+/// and CSV appends rows, so the readings can be piped into other tools.
+pub async fn watch(
+    client: &mut TuyaClient,
+    devices: DevicesResponse,
+    interval: Duration,
+    output: OutputFormat,
+) -> AppResult<()> {
+    if output == OutputFormat::CsvWide {
+        return Err(AppError::InvalidArgument(
+            "Readings can't be exported as wide CSV, use --output csv".into(),
+        ));
+    }
+
+    let mut csv = csv::Writer::from_writer(io::stdout());
+    let mut polls = 0;
+
+    poll::every(interval, async || {
+        let poll = poll(client, &devices).await;
+        match output {
+            OutputFormat::Pretty => print_table(&poll, interval),
+            OutputFormat::Json => print_json(&poll),
+            OutputFormat::Csv => poll.write_csv(&mut csv, polls == 0)?,
+            OutputFormat::CsvWide => unreachable!("Rejected before polling"),
+        }
+        polls += 1;
+        Ok(())
+    })
+    .await
+}
+
+async fn poll(client: &mut TuyaClient, devices: &DevicesResponse) -> Poll {
+    let mut readings = Vec::with_capacity(devices.len());
+    for device in devices {
+        // A device that can't be read shouldn't stop the others from updating
+        let reading = match client.get_device_properties(&device.device_id).await {
+            Ok(props) => Reading::new(device.clone(), Some(&props), None),
+            Err(e) => Reading::new(device.clone(), None, Some(e.to_string().trim().into())),
+        };
+        readings.push(reading);
+    }
+
+    let now = OffsetDateTime::now_utc();
+    Poll {
+        time: now.replace_nanosecond(0).unwrap_or(now),
+        readings,
+    }
+}
+
+impl Poll {
+    /// Writes one `time,device_id,device_name,power,voltage,current,error` row per reading.
+    /// The writer is kept between polls, so the header is only written with the first one.
+    fn write_csv<W: io::Write>(&self, w: &mut csv::Writer<W>, header: bool) -> csv::Result<()> {
+        if header {
+            w.write_record([
+                "time",
+                "device_id",
+                "device_name",
+                "power",
+                "voltage",
+                "current",
+                "error",
+            ])?;
+        }
+
+        let time = self.time.format(&Rfc3339).unwrap_or_default();
+        let value = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+        for r in &self.readings {
+            w.write_record([
+                time.as_str(),
+                r.device.device_id.as_str(),
+                r.device.get_name(),
+                value(r.power).as_str(),
+                value(r.voltage).as_str(),
+                value(r.current).as_str(),
+                r.error.as_deref().unwrap_or_default(),
+            ])?;
+        }
+
+        w.flush()?;
+        Ok(())
+    }
+}
+
+impl Reading {
+    fn new(
+        device: DeviceResponse,
+        props: Option<&DeviceProperties>,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            device,
+            power: props.and_then(|p| p.power),
+            voltage: props.and_then(|p| p.voltage),
+            current: props.and_then(|p| p.current),
+            error,
+        }
+    }
+}
+
+fn print_table(poll: &Poll, interval: Duration) {
+    let value = |v: Option<f64>, precision: usize, unit: &str| {
+        v.map_or("-".into(), |v| format!("{:.*} {}", precision, v, unit))
+    };
+    let mut rows = vec![["Device", "Power", "Voltage", "Current", ""].map(String::from)];
+    rows.extend(poll.readings.iter().enumerate().map(|(i, r)| {
+        [
+            format!("[{}] {}", i + 1, r.device.get_name()),
+            value(r.power, 1, "W"),
+            value(r.voltage, 1, "V"),
+            value(r.current, 3, "A"),
+            r.error.clone().unwrap_or_default(),
+        ]
+    }));
+    if poll.readings.len() > 1 {
+        let power = poll.readings.iter().filter_map(|r| r.power).sum::<f64>();
+        rows.push([
+            "Total".into(),
+            value(Some(power), 1, "W"),
+            String::new(),
+            String::new(),
+            String::new(),
+        ]);
+    }
+
+    // Names are aligned left and readings right, errors trail behind
+    let widths = (0..4)
+        .map(|c| rows.iter().map(|r| r[c].len()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    let line = |row: &[String; 5]| {
+        let mut s = format!("{: <width$}", row[0], width = widths[0]);
+        for (cell, width) in row[1..4].iter().zip(&widths[1..]) {
+            s += &format!("  {: >width$}", cell, width = width);
+        }
+        s += &format!("  {}", row[4]);
+        s.trim_end().to_string()
+    };
+
+    let time = poll
+        .time
+        .format(format_description!("[hour]:[minute]:[second]"))
+        .unwrap_or_default();
+    let mut out = io::stdout().lock();
+    // Piped output keeps every table instead of redrawing
+    let redraw = out.is_terminal();
+    if redraw {
+        write!(out, "{}", CLEAR_SCREEN).ok();
+    }
+    writeln!(
+        out,
+        "Every {}s, updated {} UTC, press Ctrl+C to stop\n",
+        interval.as_secs(),
+        time
+    )
+    .ok();
+    for row in &rows {
+        writeln!(out, "{}", line(row)).ok();
+    }
+    if !redraw {
+        writeln!(out).ok();
+    }
+    out.flush().ok();
+}